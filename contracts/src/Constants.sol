@@ -8,3 +8,5 @@ uint256 constant K = 15; // How many sections to split the trace into each turn
 uint256 constant CHALLENGE_TOTAL_TIME_BLOCKS = 30; // blocks allowed for each participant in the challenge game. Cumulitive like a chess clock
 
 uint64 constant FINALIZATION_TIME_BLOCKS = 100; // Number of blocks an update must be pending and unchallenged before it can finalize
+
+uint8 constant VERDICT_FINALIZED = 1; // Verdict code written by the state transition when the candidate checkpoint was finalized
@@ -8,7 +8,7 @@ import {IExecutor} from "./interfaces/IExecutor.sol";
 import {IExecutorIO} from "./interfaces/IExecutorIO.sol";
 import {CGOrchestrator} from "./CGOrchestrator.sol";
 
-import {FINALIZATION_TIME_BLOCKS, K} from "./Constants.sol";
+import {FINALIZATION_TIME_BLOCKS, K, VERDICT_FINALIZED} from "./Constants.sol";
 import {SSZ} from "./lib/SSZ.sol";
 
 /// @title Zipline Block Oracle
@@ -139,11 +139,6 @@ contract Zipline is CGOrchestrator {
             "Submission has already reached finality. Call finalize"
         );
 
-        // check that this final snapshot has terminated and validates to true
-        require(
-            io.readOutput(finalSnapshot) == bytes32(0x00), "Challengers final output must signal a correct verification"
-        );
-
         // the rival checkpoint must be incompatible with the checkpoint it is challenging
         // i.e. it must be impossible for both to have been finalized by Casper FFG
         // In simple terms this just means they have the same epoch number but different block roots
@@ -159,6 +154,13 @@ contract Zipline is CGOrchestrator {
         bytes32 trustedblockRoot = trustedBlockRoots[epoch - 1] | pendingSubmissions[epoch - 1].blockRoot;
 
         bytes32 inputHash = computeInputHash(epoch - 1, trustedblockRoot, epoch, rivalBlockRoot, proofData);
+
+        // check that this final snapshot has terminated and reports the rival checkpoint as finalized for this input
+        require(
+            io.readOutput(finalSnapshot) == computeOutputHash(inputHash, epoch, rivalBlockRoot, VERDICT_FINALIZED),
+            "Challengers final output must signal a correct verification"
+        );
+
         bytes32 startSnapshot = io.writeInput(goldenSnapshot, inputHash);
 
         bytes32[2] memory startAndEndSnapshots;
@@ -237,6 +239,23 @@ contract Zipline is CGOrchestrator {
         return sha256(proofData);
     }
 
+    /// @notice Compute the output hash the state transition writes for a given input and verdict.
+    /// This is the SSZ hash tree root of the ZiplineOutput container (input hash, candidate checkpoint, verdict)
+    function computeOutputHash(bytes32 inputHash, uint64 candidateEpoch, bytes32 candidateBlockRoot, uint8 verdict)
+        public
+        pure
+        returns (bytes32)
+    {
+        bytes32 candidateRoot =
+            sha256(abi.encodePacked(SSZ.uint64ToLittleEndian(candidateEpoch), bytes24(0), candidateBlockRoot));
+        bytes32 verdictLeaf = bytes32(bytes1(verdict));
+        return sha256(
+            abi.encodePacked(
+                sha256(abi.encodePacked(inputHash, candidateRoot)), sha256(abi.encodePacked(verdictLeaf, bytes32(0)))
+            )
+        );
+    }
+
     // Called when a Submission was successfully proven to be fraudulent
     function SubmissionChallengeSuccess(uint64 epoch, uint256 challengeId) private {
         delete openChallenges[challengeId];
@@ -1,23 +1,43 @@
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use blst::min_pk as bls;
-use blst::BLST_ERROR;
+use blst::{blst_p1_affine, blst_p2_affine, blst_scalar, Pairing, BLST_ERROR};
+
+use crate::hash::{Sha256Context, HASH_LEN};
 
 // domain string, must match what is used in signing. This one should be good for beacon chain
 const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
 
 pub const BLS_SIGNATURE_BYTES_LEN: usize = 96;
+pub const BLS_PUBLIC_KEY_BYTES_LEN: usize = 48;
+pub const BLS_PUBLIC_KEY_UNCOMPRESSED_BYTES_LEN: usize = 96;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BlsError {
     InvalidSignature,
+    /// The bytes are not the encoding of a point on the curve
+    BadEncoding,
+    /// The point is on the curve but not in the prime order subgroup
+    PointNotInGroup,
+    /// The public key is the identity point
+    PublicKeyIsInfinity,
+    /// There were no keys or signatures to aggregate
+    EmptyAggregate,
     Other(String),
 }
 
 impl From<BLST_ERROR> for BlsError {
     fn from(value: BLST_ERROR) -> Self {
-        assert!(value != BLST_ERROR::BLST_SUCCESS);
-        Self::Other(format_args!("{:?}", value).to_string())
+        match value {
+            BLST_ERROR::BLST_BAD_ENCODING | BLST_ERROR::BLST_POINT_NOT_ON_CURVE => {
+                Self::BadEncoding
+            }
+            BLST_ERROR::BLST_POINT_NOT_IN_GROUP => Self::PointNotInGroup,
+            BLST_ERROR::BLST_PK_IS_INFINITY => Self::PublicKeyIsInfinity,
+            // success is not an error. Converting it is a bug in the caller but shouldn't panic
+            BLST_ERROR::BLST_SUCCESS => Self::Other("unexpected BLST_SUCCESS".to_string()),
+            _ => Self::Other(format_args!("{:?}", value).to_string()),
+        }
     }
 }
 
@@ -27,33 +47,80 @@ impl From<String> for BlsError {
     }
 }
 #[derive(Clone, Debug)]
-pub struct PublicKey(bls::PublicKey);
+pub struct PublicKey(pub(crate) bls::PublicKey);
 
 impl PublicKey {
+    /// Decode a compressed public key. As in the spec's `KeyValidate` the key must be in the prime order subgroup and
+    /// not the identity point
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, BlsError> {
-        Ok(PublicKey(bls::PublicKey::from_bytes(bytes).unwrap()))
+        let public_key = PublicKey(bls::PublicKey::from_bytes(bytes)?);
+        public_key.key_validate()?;
+        Ok(public_key)
+    }
+
+    /// The spec's `KeyValidate`. Check the key is in the prime order subgroup and not the identity point.
+    /// Keys from `from_bytes` and `from_uncompressed` have already been checked
+    pub fn key_validate(&self) -> Result<(), BlsError> {
+        self.0.validate().map_err(Into::into)
     }
 
     pub fn to_bytes(&self) -> [u8; 48] {
         self.0.to_bytes()
     }
+
+    /// Construct from an uncompressed (x, y) point. Unlike decompressing this does not require computing a square root.
+    /// The key is validated the same as in `from_bytes`
+    pub fn from_uncompressed(bytes: &[u8]) -> Result<Self, BlsError> {
+        // the compression flag must not be set otherwise this would decompress
+        if bytes.len() != BLS_PUBLIC_KEY_UNCOMPRESSED_BYTES_LEN || bytes[0] & 0x80 != 0 {
+            return Err(BlsError::BadEncoding);
+        }
+        let public_key = PublicKey(bls::PublicKey::deserialize(bytes)?);
+        public_key.key_validate()?;
+        Ok(public_key)
+    }
+
+    pub fn to_uncompressed_bytes(&self) -> [u8; BLS_PUBLIC_KEY_UNCOMPRESSED_BYTES_LEN] {
+        self.0.serialize()
+    }
     #[allow(clippy::should_implement_trait)]
-    pub fn add(self, other: PublicKey) -> Self {
+    pub fn add(self, other: PublicKey) -> Result<Self, BlsError> {
         let mut aggkey = bls::AggregatePublicKey::from_public_key(&self.0);
-        aggkey.add_public_key(&other.0, false).unwrap();
-        Self(aggkey.to_public_key())
+        aggkey.add_public_key(&other.0, false)?;
+        Ok(Self(aggkey.to_public_key()))
+    }
+
+    pub fn aggregate(public_keys: &[PublicKey]) -> Result<Self, BlsError> {
+        if public_keys.is_empty() {
+            return Err(BlsError::EmptyAggregate);
+        }
+        let public_keys = public_keys.iter().map(|k| &k.0).collect::<Vec<_>>();
+        let aggkey = bls::AggregatePublicKey::aggregate(&public_keys, false)?;
+        Ok(Self(aggkey.to_public_key()))
     }
 }
 
+// Signatures are checked to be in the prime order subgroup when decoded so verifying does not repeat the check
 pub struct Signature(bls::Signature);
 
 impl Signature {
+    /// Decode a compressed signature, which must be in the prime order subgroup. The identity point is allowed as it
+    /// can be the aggregate of valid signatures
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, BlsError> {
-        Ok(Signature(bls::Signature::from_bytes(bytes)?))
+        Ok(Signature(bls::Signature::sig_validate(bytes, false)?))
     }
     pub fn to_bytes(&self) -> [u8; 96] {
         self.0.to_bytes()
     }
+
+    pub fn aggregate(signatures: &[Signature]) -> Result<Self, BlsError> {
+        if signatures.is_empty() {
+            return Err(BlsError::EmptyAggregate);
+        }
+        let signatures = signatures.iter().map(|s| &s.0).collect::<Vec<_>>();
+        let aggsig = bls::AggregateSignature::aggregate(&signatures, false)?;
+        Ok(Self(aggsig.to_signature()))
+    }
 }
 
 pub fn verify_signature(
@@ -61,7 +128,9 @@ pub fn verify_signature(
     msg: &[u8],
     signature: &Signature,
 ) -> Result<(), BlsError> {
-    let res = signature.0.verify(true, msg, DST, &[], &public_key.0, true);
+    let res = signature
+        .0
+        .verify(false, msg, DST, &[], &public_key.0, true);
     if res == BLST_ERROR::BLST_SUCCESS {
         Ok(())
     } else {
@@ -78,7 +147,7 @@ pub fn fast_aggregate_verify(
 
     let res = signature
         .0
-        .fast_aggregate_verify(true, msg, DST, &public_keys);
+        .fast_aggregate_verify(false, msg, DST, &public_keys);
     if res == BLST_ERROR::BLST_SUCCESS {
         Ok(())
     } else {
@@ -86,12 +155,192 @@ pub fn fast_aggregate_verify(
     }
 }
 
-// This is verification for the case where multiple messages were signed and an aggregate signature obtained by aggregating the resulting signatures.
-// TODO: BLST won't do this out of the box but it should be fairly easy to implement with their lower level operations
-pub fn multi_message_verify(
-    _messages: &[&[u8]],
-    _public_key: &PublicKey,
-    _signature: &Signature,
+// Verify an aggregate signature over distinct messages where each message was signed by the corresponding public key.
+// The public keys may themselves be aggregates of the keys that signed the same message.
+pub fn aggregate_verify(
+    public_keys: &[PublicKey],
+    messages: &[&[u8]],
+    signature: &Signature,
 ) -> Result<(), BlsError> {
+    if public_keys.len() != messages.len() {
+        return Err(BlsError::Other(
+            "number of public keys and messages differ".to_string(),
+        ));
+    }
+    verify_pairing(public_keys.iter().zip(messages.iter().copied()), signature)
+}
+
+// Bits of the random scalars used to combine the signatures in a batch. An invalid batch passes with probability
+// about 2^-128
+const BATCH_RAND_BITS: usize = 128;
+
+// separates the hash of a batch from any other use of sha256
+const BATCH_TRANSCRIPT_DST: &[u8] = b"ZIPLINE_BLS_BATCH_VERIFY_V1";
+
+/// Verify many signatures at once where each signature is over its own message by the corresponding (possibly
+/// aggregate) public key. The signatures are combined with random scalars so the whole batch needs a single
+/// multi-pairing and final exponentiation. This only tells if every signature is valid, use `find_invalid_signatures`
+/// to find which are not
+pub fn batch_verify(
+    public_keys: &[PublicKey],
+    messages: &[&[u8]],
+    signatures: &[Signature],
+) -> Result<(), BlsError> {
+    check_batch_lengths(public_keys, messages, signatures)?;
+    if public_keys.is_empty() {
+        return Ok(());
+    }
+    let rands = batch_scalars(public_keys, messages, signatures);
+    let public_keys = public_keys.iter().map(|k| &k.0).collect::<Vec<_>>();
+    let signatures = signatures.iter().map(|s| &s.0).collect::<Vec<_>>();
+
+    let res = bls::Signature::verify_multiple_aggregate_signatures(
+        messages,
+        DST,
+        &public_keys,
+        false,
+        &signatures,
+        false,
+        &rands,
+        BATCH_RAND_BITS,
+    );
+    if res == BLST_ERROR::BLST_SUCCESS {
+        Ok(())
+    } else {
+        Err(BlsError::InvalidSignature)
+    }
+}
+
+/// The indices of the invalid signatures in a batch, in order. A batch that fails is split in half and each half
+/// verified again so a few invalid signatures only cost a few more batch verifications
+pub fn find_invalid_signatures(
+    public_keys: &[PublicKey],
+    messages: &[&[u8]],
+    signatures: &[Signature],
+) -> Result<Vec<usize>, BlsError> {
+    check_batch_lengths(public_keys, messages, signatures)?;
+    let mut invalid = Vec::new();
+    bisect_invalid_signatures(public_keys, messages, signatures, 0, &mut invalid);
+    Ok(invalid)
+}
+
+fn bisect_invalid_signatures(
+    public_keys: &[PublicKey],
+    messages: &[&[u8]],
+    signatures: &[Signature],
+    offset: usize,
+    invalid: &mut Vec<usize>,
+) {
+    if batch_verify(public_keys, messages, signatures).is_ok() {
+        return;
+    }
+    if public_keys.len() == 1 {
+        invalid.push(offset);
+        return;
+    }
+    let mid = public_keys.len() / 2;
+    bisect_invalid_signatures(
+        &public_keys[..mid],
+        &messages[..mid],
+        &signatures[..mid],
+        offset,
+        invalid,
+    );
+    bisect_invalid_signatures(
+        &public_keys[mid..],
+        &messages[mid..],
+        &signatures[mid..],
+        offset + mid,
+        invalid,
+    );
+}
+
+fn check_batch_lengths(
+    public_keys: &[PublicKey],
+    messages: &[&[u8]],
+    signatures: &[Signature],
+) -> Result<(), BlsError> {
+    if public_keys.len() != messages.len() || public_keys.len() != signatures.len() {
+        return Err(BlsError::Other(
+            "number of public keys, messages and signatures differ".to_string(),
+        ));
+    }
     Ok(())
 }
+
+// There is no source of randomness in the MIPS VM so the scalars are derived by hashing the whole batch. Every public
+// key, message and signature is fixed before the scalars are known so they can't be chosen to cancel each other out.
+// Messages are length prefixed and the batch size is included so no two batches share a transcript
+pub(crate) fn batch_scalars(
+    public_keys: &[PublicKey],
+    messages: &[&[u8]],
+    signatures: &[Signature],
+) -> Vec<blst_scalar> {
+    let mut ctx = <sha2::Sha256 as Sha256Context>::new();
+    Sha256Context::update(&mut ctx, BATCH_TRANSCRIPT_DST);
+    Sha256Context::update(&mut ctx, &(public_keys.len() as u64).to_le_bytes());
+    for ((public_key, message), signature) in public_keys.iter().zip(messages).zip(signatures) {
+        Sha256Context::update(&mut ctx, &public_key.to_bytes());
+        Sha256Context::update(&mut ctx, &(message.len() as u64).to_le_bytes());
+        Sha256Context::update(&mut ctx, message);
+        Sha256Context::update(&mut ctx, &signature.to_bytes());
+    }
+    let seed: [u8; HASH_LEN] = Sha256Context::finalize(ctx);
+
+    (0..public_keys.len() as u64)
+        .map(|i| {
+            let mut ctx = <sha2::Sha256 as Sha256Context>::new();
+            Sha256Context::update(&mut ctx, &seed);
+            Sha256Context::update(&mut ctx, &i.to_le_bytes());
+            let mut scalar = blst_scalar::default();
+            scalar.b[..BATCH_RAND_BITS / 8]
+                .copy_from_slice(&Sha256Context::finalize(ctx)[..BATCH_RAND_BITS / 8]);
+            // a zero scalar would drop the signature from the check
+            scalar.b[0] |= 1;
+            scalar
+        })
+        .collect()
+}
+
+// This is verification for the case where multiple messages were signed by the same key and an aggregate signature
+// obtained by aggregating the resulting signatures.
+pub fn multi_message_verify(
+    messages: &[&[u8]],
+    public_key: &PublicKey,
+    signature: &Signature,
+) -> Result<(), BlsError> {
+    verify_pairing(messages.iter().map(|m| (public_key, *m)), signature)
+}
+
+// Check e(g1, signature) is the product of e(public_key, H(message)) over the pairs. There must be at least one pair and
+// public keys at infinity are always rejected
+fn verify_pairing<'a>(
+    pairs: impl Iterator<Item = (&'a PublicKey, &'a [u8])>,
+    signature: &Signature,
+) -> Result<(), BlsError> {
+    let mut pairing = Pairing::new(true, DST);
+    let signature: blst_p2_affine = signature.0.into();
+    let mut n_pairs = 0;
+    for (public_key, message) in pairs {
+        let public_key: blst_p1_affine = public_key.0.into();
+        // the signature is added to the pairing once, with the first message
+        let res = if n_pairs == 0 {
+            pairing.aggregate(&public_key, false, &signature, false, message, &[])
+        } else {
+            pairing.aggregate(&public_key, false, &(), false, message, &[])
+        };
+        if res != BLST_ERROR::BLST_SUCCESS {
+            return Err(BlsError::InvalidSignature);
+        }
+        n_pairs += 1;
+    }
+    if n_pairs == 0 {
+        return Err(BlsError::InvalidSignature);
+    }
+    pairing.commit();
+    if pairing.finalverify(None) {
+        Ok(())
+    } else {
+        Err(BlsError::InvalidSignature)
+    }
+}
@@ -0,0 +1,320 @@
+use alloc::vec::Vec;
+use blst::min_pk::SecretKey;
+use blst::{
+    blst_fp, blst_fp2, blst_fp2_add, blst_fp2_mul, blst_fp2_sqr, blst_fp2_sqrt, blst_fp_add,
+    blst_fp_from_uint64, blst_fp_mul, blst_fp_sqr, blst_fp_sqrt, blst_p1_affine,
+    blst_p1_affine_compress, blst_p1_affine_in_g1, blst_p1_affine_serialize, blst_p2_affine,
+    blst_p2_affine_compress, blst_p2_affine_in_g2, BLST_ERROR,
+};
+
+use crate::bls::{
+    aggregate_verify, batch_scalars, batch_verify, find_invalid_signatures, multi_message_verify,
+    BlsError, PublicKey, Signature,
+};
+
+const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+struct Batch {
+    public_keys: Vec<PublicKey>,
+    messages: Vec<[u8; 32]>,
+    signatures: Vec<Signature>,
+}
+
+impl Batch {
+    // one key signing a distinct message for each signature
+    fn signed(n: u8) -> Self {
+        let mut batch = Batch {
+            public_keys: Vec::new(),
+            messages: Vec::new(),
+            signatures: Vec::new(),
+        };
+        for i in 0..n {
+            let sk = SecretKey::key_gen(&[i + 1; 32], &[]).unwrap();
+            let message = [i; 32];
+            batch
+                .public_keys
+                .push(PublicKey::from_bytes(&sk.sk_to_pk().to_bytes()).unwrap());
+            batch
+                .signatures
+                .push(Signature::from_bytes(&sk.sign(&message, DST, &[]).to_bytes()).unwrap());
+            batch.messages.push(message);
+        }
+        batch
+    }
+
+    // the signature at `i` no longer matches its message
+    fn tamper(&mut self, i: usize) {
+        self.signatures[i] = Signature::from_bytes(&self.signatures[i ^ 1].to_bytes()).unwrap();
+    }
+
+    fn messages(&self) -> Vec<&[u8]> {
+        self.messages.iter().map(|m| m.as_ref()).collect()
+    }
+
+    fn verify(&self) -> bool {
+        batch_verify(&self.public_keys, &self.messages(), &self.signatures).is_ok()
+    }
+
+    fn invalid(&self) -> Vec<usize> {
+        find_invalid_signatures(&self.public_keys, &self.messages(), &self.signatures).unwrap()
+    }
+}
+
+#[test]
+fn batch_of_valid_signatures_verifies() {
+    let batch = Batch::signed(8);
+    assert!(batch.verify());
+    assert!(batch.invalid().is_empty());
+}
+
+#[test]
+fn empty_batch_verifies() {
+    assert!(Batch::signed(0).verify());
+}
+
+#[test]
+fn batch_with_invalid_signature_fails() {
+    let mut batch = Batch::signed(8);
+    batch.tamper(5);
+    assert!(!batch.verify());
+    assert_eq!(batch.invalid(), [5]);
+}
+
+#[test]
+fn bisection_finds_every_invalid_signature() {
+    let mut batch = Batch::signed(12);
+    for i in [0, 3, 4, 10] {
+        batch.tamper(i);
+    }
+    assert_eq!(batch.invalid(), [0, 3, 4, 10]);
+}
+
+#[test]
+fn batch_rejects_mismatched_lengths() {
+    let batch = Batch::signed(2);
+    assert!(batch_verify(
+        &batch.public_keys,
+        &batch.messages()[..1],
+        &batch.signatures
+    )
+    .is_err());
+}
+
+#[test]
+fn batch_scalars_are_128_bits_and_bind_the_whole_batch() {
+    let batch = Batch::signed(4);
+    let scalars = batch_scalars(&batch.public_keys, &batch.messages(), &batch.signatures);
+    assert!(scalars.iter().all(|s| s.b[16..].iter().all(|b| *b == 0)));
+    assert!(scalars.iter().all(|s| s.b[8..16].iter().any(|b| *b != 0)));
+
+    // changing any key, message or signature changes every scalar
+    let mut public_keys = batch.public_keys.clone();
+    public_keys.swap(2, 3);
+    let mut messages = batch.messages();
+    messages[3] = &batch.messages[0][..31];
+    let mut signatures: Vec<Signature> = batch
+        .signatures
+        .iter()
+        .map(|s| Signature::from_bytes(&s.to_bytes()).unwrap())
+        .collect();
+    signatures.swap(0, 1);
+    for changed in [
+        batch_scalars(&public_keys, &batch.messages(), &batch.signatures),
+        batch_scalars(&batch.public_keys, &messages, &batch.signatures),
+        batch_scalars(&batch.public_keys, &batch.messages(), &signatures),
+    ] {
+        assert!(scalars.iter().zip(&changed).all(|(a, b)| a.b != b.b));
+    }
+}
+
+fn aggregate(signatures: &[Signature]) -> Signature {
+    Signature::aggregate(signatures).unwrap()
+}
+
+#[test]
+fn aggregate_verify_distinct_keys_and_messages() {
+    let batch = Batch::signed(4);
+    let signature = aggregate(&batch.signatures);
+    assert!(aggregate_verify(&batch.public_keys, &batch.messages(), &signature).is_ok());
+
+    // dropping a message leaves its signature unaccounted for
+    assert!(aggregate_verify(&batch.public_keys[1..], &batch.messages()[1..], &signature).is_err());
+    // swapping messages between keys
+    let mut messages = batch.messages();
+    messages.swap(0, 1);
+    assert!(aggregate_verify(&batch.public_keys, &messages, &signature).is_err());
+}
+
+#[test]
+fn aggregate_verify_rejects_no_public_keys() {
+    let batch = Batch::signed(1);
+    assert!(aggregate_verify(&[], &[], &batch.signatures[0]).is_err());
+}
+
+#[test]
+fn multi_message_verify_one_key_many_messages() {
+    let sk = SecretKey::key_gen(&[7; 32], &[]).unwrap();
+    let public_key = PublicKey::from_bytes(&sk.sk_to_pk().to_bytes()).unwrap();
+    let messages = [[1u8; 32], [2; 32], [3; 32]];
+    let signatures = messages
+        .iter()
+        .map(|m| Signature::from_bytes(&sk.sign(m, DST, &[]).to_bytes()).unwrap())
+        .collect::<Vec<_>>();
+    let messages = messages.iter().map(|m| m.as_ref()).collect::<Vec<_>>();
+    let signature = aggregate(&signatures);
+
+    assert!(multi_message_verify(&messages, &public_key, &signature).is_ok());
+    assert!(multi_message_verify(&messages[..2], &public_key, &signature).is_err());
+    assert!(multi_message_verify(&[], &public_key, &signature).is_err());
+
+    let other_key = Batch::signed(1).public_keys.remove(0);
+    assert!(multi_message_verify(&messages, &other_key, &signature).is_err());
+}
+
+fn fp(n: u64) -> blst_fp {
+    let mut fp = blst_fp::default();
+    unsafe { blst_fp_from_uint64(&mut fp, [n, 0, 0, 0, 0, 0].as_ptr()) };
+    fp
+}
+
+// Points on the curves are found by trying small x until x^3 + b is a square. The subgroups have a large cofactor in
+// the groups of points on the curves so these points are not in them
+fn g1_point_not_in_group() -> blst_p1_affine {
+    for x in 1.. {
+        let x = fp(x);
+        let mut rhs = blst_fp::default();
+        let mut y = blst_fp::default();
+        let r: *mut blst_fp = &mut rhs;
+        unsafe {
+            blst_fp_sqr(r, &x);
+            blst_fp_mul(r, r, &x);
+            blst_fp_add(r, r, &fp(4));
+            if blst_fp_sqrt(&mut y, r) {
+                let point = blst_p1_affine { x, y };
+                assert!(!blst_p1_affine_in_g1(&point));
+                return point;
+            }
+        }
+    }
+    unreachable!()
+}
+
+fn g2_point_not_in_group() -> blst_p2_affine {
+    let b = blst_fp2 { fp: [fp(4), fp(4)] };
+    for x in 1.. {
+        let x = blst_fp2 { fp: [fp(x), fp(0)] };
+        let mut rhs = blst_fp2::default();
+        let mut y = blst_fp2::default();
+        let r: *mut blst_fp2 = &mut rhs;
+        unsafe {
+            blst_fp2_sqr(r, &x);
+            blst_fp2_mul(r, r, &x);
+            blst_fp2_add(r, r, &b);
+            if blst_fp2_sqrt(&mut y, r) {
+                let point = blst_p2_affine { x, y };
+                assert!(!blst_p2_affine_in_g2(&point));
+                return point;
+            }
+        }
+    }
+    unreachable!()
+}
+
+#[test]
+fn public_key_decoding_rejects_invalid_keys() {
+    let mut infinity = [0u8; 48];
+    infinity[0] = 0xc0;
+    assert_eq!(
+        PublicKey::from_bytes(&infinity).unwrap_err(),
+        BlsError::PublicKeyIsInfinity
+    );
+    assert_eq!(
+        PublicKey::from_bytes(&[0xff; 48]).unwrap_err(),
+        BlsError::BadEncoding
+    );
+    assert_eq!(
+        PublicKey::from_bytes(&[0xaa; 47]).unwrap_err(),
+        BlsError::BadEncoding
+    );
+
+    let mut compressed = [0u8; 48];
+    unsafe { blst_p1_affine_compress(compressed.as_mut_ptr(), &g1_point_not_in_group()) };
+    assert_eq!(
+        PublicKey::from_bytes(&compressed).unwrap_err(),
+        BlsError::PointNotInGroup
+    );
+}
+
+#[test]
+fn uncompressed_public_keys_are_validated() {
+    let mut uncompressed = [0u8; 96];
+    unsafe { blst_p1_affine_serialize(uncompressed.as_mut_ptr(), &g1_point_not_in_group()) };
+    assert_eq!(
+        PublicKey::from_uncompressed(&uncompressed).unwrap_err(),
+        BlsError::PointNotInGroup
+    );
+
+    // the identity point has only the infinity flag set
+    let mut infinity = [0u8; 96];
+    infinity[0] = 0x40;
+    assert_eq!(
+        PublicKey::from_uncompressed(&infinity).unwrap_err(),
+        BlsError::PublicKeyIsInfinity
+    );
+
+    let batch = Batch::signed(1);
+    let uncompressed = batch.public_keys[0].to_uncompressed_bytes();
+    assert!(PublicKey::from_uncompressed(&uncompressed).is_ok());
+}
+
+#[test]
+fn key_validate_rejects_keys_outside_the_group() {
+    let batch = Batch::signed(1);
+    assert_eq!(batch.public_keys[0].key_validate(), Ok(()));
+
+    // deserializing only checks the point is on the curve so the key is not validated
+    let mut uncompressed = [0u8; 96];
+    unsafe { blst_p1_affine_serialize(uncompressed.as_mut_ptr(), &g1_point_not_in_group()) };
+    let not_in_group = PublicKey(blst::min_pk::PublicKey::deserialize(&uncompressed).unwrap());
+    assert_eq!(not_in_group.key_validate(), Err(BlsError::PointNotInGroup));
+}
+
+#[test]
+fn converting_blst_success_is_not_a_panic() {
+    assert!(matches!(
+        BlsError::from(BLST_ERROR::BLST_SUCCESS),
+        BlsError::Other(_)
+    ));
+}
+
+#[test]
+fn signature_decoding_rejects_points_not_in_group() {
+    let mut compressed = [0u8; 96];
+    unsafe { blst_p2_affine_compress(compressed.as_mut_ptr(), &g2_point_not_in_group()) };
+    assert_eq!(
+        Signature::from_bytes(&compressed).err(),
+        Some(BlsError::PointNotInGroup)
+    );
+}
+
+#[test]
+fn aggregating_nothing_is_an_error() {
+    assert_eq!(
+        PublicKey::aggregate(&[]).unwrap_err(),
+        BlsError::EmptyAggregate
+    );
+    assert_eq!(
+        Signature::aggregate(&[]).err(),
+        Some(BlsError::EmptyAggregate)
+    );
+
+    let batch = Batch::signed(2);
+    let sum = batch.public_keys[0]
+        .clone()
+        .add(batch.public_keys[1].clone())
+        .unwrap();
+    assert_eq!(
+        sum.to_bytes(),
+        PublicKey::aggregate(&batch.public_keys).unwrap().to_bytes()
+    );
+}
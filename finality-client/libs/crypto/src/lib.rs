@@ -4,5 +4,7 @@ extern crate alloc;
 pub mod bls;
 pub mod hash;
 
+#[cfg(test)]
+mod bls_tests;
 #[cfg(test)]
 mod spec_tests;
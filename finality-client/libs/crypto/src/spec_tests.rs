@@ -1,4 +1,6 @@
-use alloc::string::ToString;
+use crate::bls;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use ethereum_consensus::crypto::{verify_signature, PublicKey, Signature};
 use ethereum_consensus::primitives::Bytes32;
 use serde::Deserialize;
@@ -280,3 +282,120 @@ fn test_verify_wrong_pubkey_case_e_8_a_50_c_445_c_855360() {
 
     test_case.execute();
 }
+
+#[derive(Debug, Deserialize)]
+struct AggregateVerifyInput {
+    pubkeys: Vec<String>,
+    messages: Vec<String>,
+    signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AggregateVerifyTestCase {
+    input: AggregateVerifyInput,
+    output: bool,
+}
+
+impl AggregateVerifyTestCase {
+    pub fn from(test_case_path: &str) -> Self {
+        let path = test_case_path.to_string() + "/data.yaml";
+        load_yaml(&path)
+    }
+
+    // runs our implementation rather than ethereum-consensus. Inputs that can't be decoded never verify
+    fn run(&self) -> bool {
+        let public_keys = self
+            .input
+            .pubkeys
+            .iter()
+            .map(|k| bls::PublicKey::from_bytes(&hex_bytes(k)))
+            .collect::<Result<Vec<_>, _>>();
+        let signature = bls::Signature::from_bytes(&hex_bytes(&self.input.signature));
+        let (Ok(public_keys), Ok(signature)) = (public_keys, signature) else {
+            return false;
+        };
+        let messages = self
+            .input
+            .messages
+            .iter()
+            .map(|m| hex_bytes(m))
+            .collect::<Vec<_>>();
+        let messages = messages.iter().map(|m| m.as_slice()).collect::<Vec<_>>();
+        bls::aggregate_verify(&public_keys, &messages, &signature).is_ok()
+    }
+}
+
+impl TestCase for AggregateVerifyTestCase {
+    fn should_succeed(&self) -> bool {
+        self.output
+    }
+
+    fn verify_success(&self) -> bool {
+        self.run()
+    }
+
+    fn verify_failure(&self) -> bool {
+        !self.run()
+    }
+}
+
+fn hex_bytes(s: &str) -> Vec<u8> {
+    let s = s.trim_start_matches("0x");
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+macro_rules! aggregate_verify_test_path {
+    ($t:literal) => {
+        concat!(
+            "../../../consensus-spec-tests/tests/general/phase0/bls/aggregate_verify/small/",
+            $t
+        )
+    };
+}
+
+#[test]
+fn test_aggregate_verify_infinite_pubkey() {
+    let test_case = AggregateVerifyTestCase::from(aggregate_verify_test_path!(
+        "aggregate_verify_infinite_pubkey"
+    ));
+
+    test_case.execute();
+}
+
+#[test]
+fn test_aggregate_verify_na_pubkeys_and_infinity_signature() {
+    let test_case = AggregateVerifyTestCase::from(aggregate_verify_test_path!(
+        "aggregate_verify_na_pubkeys_and_infinity_signature"
+    ));
+
+    test_case.execute();
+}
+
+#[test]
+fn test_aggregate_verify_na_pubkeys_and_na_signature() {
+    let test_case = AggregateVerifyTestCase::from(aggregate_verify_test_path!(
+        "aggregate_verify_na_pubkeys_and_na_signature"
+    ));
+
+    test_case.execute();
+}
+
+#[test]
+fn test_aggregate_verify_tampered_signature() {
+    let test_case = AggregateVerifyTestCase::from(aggregate_verify_test_path!(
+        "aggregate_verify_tampered_signature"
+    ));
+
+    test_case.execute();
+}
+
+#[test]
+fn test_aggregate_verify_valid() {
+    let test_case =
+        AggregateVerifyTestCase::from(aggregate_verify_test_path!("aggregate_verify_valid"));
+
+    test_case.execute();
+}
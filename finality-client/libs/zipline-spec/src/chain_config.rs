@@ -0,0 +1,468 @@
+//! Runtime configuration for off-chain tooling.
+//!
+//! The compile time specs are what the MIPS build uses. Std hosts can instead load a [`ChainConfig`] from the
+//! consensus-specs `config.yaml` and preset files and use [`RuntimeSpec`] anywhere a [`Spec`] is expected.
+
+use crate::gindex::{
+    ContainerLayout, ALTAIR_BEACON_STATE, BELLATRIX_BEACON_STATE, CAPELLA_BEACON_STATE,
+    ELECTRA_BEACON_STATE, FULU_BEACON_STATE, PHASE0_BEACON_STATE,
+};
+use crate::{Fork, Spec};
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use ssz_rs::prelude::Node;
+use std::sync::OnceLock;
+use typenum::{Exp, Prod, U1, U10, U128, U16, U32, U4, U64, U65536, U8192, U9, U90};
+
+// signing domain types are fixed by the spec and not part of the config files
+const DOMAIN_BEACON_ATTESTER: u32 = 1;
+
+// forks in the order their (optional) version and epoch appear in config.yaml, with their BeaconState layout
+const FORKS: &[(&str, ContainerLayout)] = &[
+    ("ALTAIR", ALTAIR_BEACON_STATE),
+    ("BELLATRIX", BELLATRIX_BEACON_STATE),
+    ("CAPELLA", CAPELLA_BEACON_STATE),
+    ("DENEB", CAPELLA_BEACON_STATE),
+    ("ELECTRA", ELECTRA_BEACON_STATE),
+    ("FULU", FULU_BEACON_STATE),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    MissingKey(&'static str),
+    InvalidValue { key: String, value: String },
+    // fork epochs must be non-decreasing in the order the forks were introduced
+    UnorderedForks,
+    // RuntimeSpec's config can only be installed once per process
+    AlreadyInstalled,
+}
+
+/// Chain parameters loaded at runtime. Provides the same accessors as [`Spec`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainConfig {
+    slots_per_epoch: usize,
+    max_committees_per_slot: usize,
+    target_committee_size: usize,
+    shuffle_round_count: u8,
+    min_seed_lookahead: usize,
+    max_seed_lookahead: usize,
+    epochs_per_historical_vector: usize,
+    min_per_epoch_churn_limit: u32,
+    churn_limit_quotient: u32,
+    max_deposits: u32,
+    max_effective_balance: u64,
+    effective_balance_increment: u64,
+    slots_per_historical_root: usize,
+    forks: Vec<Fork>,
+    genesis_validators_root: Node,
+}
+
+impl ChainConfig {
+    /// Load from the contents of a network's `config.yaml` and its preset files (eg `phase0.yaml`).
+    /// Later documents override keys in earlier ones. The genesis validators root is not part of the
+    /// config files so must be given separately
+    pub fn from_yaml(
+        documents: &[&str],
+        genesis_validators_root: Node,
+    ) -> Result<Self, ConfigError> {
+        let mut values = BTreeMap::new();
+        for document in documents {
+            parse_flat_yaml(document, &mut values);
+        }
+
+        let mut forks = Vec::new();
+        forks.push(Fork::new(
+            0,
+            version(&values, "GENESIS_FORK_VERSION")?
+                .ok_or(ConfigError::MissingKey("GENESIS_FORK_VERSION"))?,
+            PHASE0_BEACON_STATE,
+        ));
+        for (name, state) in FORKS {
+            let version_key = alloc::format!("{}_FORK_VERSION", name);
+            let epoch_key = alloc::format!("{}_FORK_EPOCH", name);
+            if let (Some(version), Some(epoch)) = (
+                version(&values, &version_key)?,
+                optional_number::<u64>(&values, &epoch_key)?,
+            ) {
+                forks.push(Fork::new(epoch, version, *state));
+            }
+        }
+        if forks.windows(2).any(|w| w[0].epoch > w[1].epoch) {
+            return Err(ConfigError::UnorderedForks);
+        }
+
+        Ok(Self {
+            slots_per_epoch: number(&values, "SLOTS_PER_EPOCH")?,
+            max_committees_per_slot: number(&values, "MAX_COMMITTEES_PER_SLOT")?,
+            target_committee_size: number(&values, "TARGET_COMMITTEE_SIZE")?,
+            shuffle_round_count: number(&values, "SHUFFLE_ROUND_COUNT")?,
+            min_seed_lookahead: number(&values, "MIN_SEED_LOOKAHEAD")?,
+            max_seed_lookahead: number(&values, "MAX_SEED_LOOKAHEAD")?,
+            epochs_per_historical_vector: number(&values, "EPOCHS_PER_HISTORICAL_VECTOR")?,
+            min_per_epoch_churn_limit: number(&values, "MIN_PER_EPOCH_CHURN_LIMIT")?,
+            churn_limit_quotient: number(&values, "CHURN_LIMIT_QUOTIENT")?,
+            max_deposits: number(&values, "MAX_DEPOSITS")?,
+            max_effective_balance: number(&values, "MAX_EFFECTIVE_BALANCE")?,
+            effective_balance_increment: number(&values, "EFFECTIVE_BALANCE_INCREMENT")?,
+            slots_per_historical_root: number(&values, "SLOTS_PER_HISTORICAL_ROOT")?,
+            forks,
+            genesis_validators_root,
+        })
+    }
+
+    /// Capture the parameters of a compile time spec
+    pub fn from_spec<S: Spec>() -> Self {
+        Self {
+            slots_per_epoch: S::slots_per_epoch(),
+            max_committees_per_slot: S::max_committees_per_slot(),
+            target_committee_size: S::target_committee_size(),
+            shuffle_round_count: S::shuffle_count_count(),
+            min_seed_lookahead: S::min_seed_lookahead(),
+            max_seed_lookahead: S::max_seed_lookahead(),
+            epochs_per_historical_vector: S::epochs_per_historical_vector(),
+            min_per_epoch_churn_limit: S::min_per_epoch_churn_limit(),
+            churn_limit_quotient: S::churn_limit_quotient(),
+            max_deposits: S::max_deposits(),
+            max_effective_balance: S::max_effective_balance(),
+            effective_balance_increment: S::effective_balance_increment(),
+            slots_per_historical_root: S::slots_per_historical_root(),
+            forks: S::fork_schedule().to_vec(),
+            genesis_validators_root: S::genesis_validators_root(),
+        }
+    }
+
+    pub fn slots_per_epoch(&self) -> usize {
+        self.slots_per_epoch
+    }
+
+    pub fn max_committees_per_slot(&self) -> usize {
+        self.max_committees_per_slot
+    }
+
+    pub fn target_committee_size(&self) -> usize {
+        self.target_committee_size
+    }
+
+    pub fn shuffle_count_count(&self) -> u8 {
+        self.shuffle_round_count
+    }
+
+    pub fn min_seed_lookahead(&self) -> usize {
+        self.min_seed_lookahead
+    }
+
+    pub fn max_seed_lookahead(&self) -> usize {
+        self.max_seed_lookahead
+    }
+
+    pub fn epochs_per_historical_vector(&self) -> usize {
+        self.epochs_per_historical_vector
+    }
+
+    pub fn domain_beacon_attester(&self) -> u32 {
+        DOMAIN_BEACON_ATTESTER
+    }
+
+    pub fn min_per_epoch_churn_limit(&self) -> u32 {
+        self.min_per_epoch_churn_limit
+    }
+
+    pub fn churn_limit_quotient(&self) -> u32 {
+        self.churn_limit_quotient
+    }
+
+    pub fn max_deposits(&self) -> u32 {
+        self.max_deposits
+    }
+
+    pub fn max_effective_balance(&self) -> u64 {
+        self.max_effective_balance
+    }
+
+    pub fn effective_balance_increment(&self) -> u64 {
+        self.effective_balance_increment
+    }
+
+    pub fn slots_per_historical_root(&self) -> usize {
+        self.slots_per_historical_root
+    }
+
+    pub fn fork_schedule(&self) -> &[Fork] {
+        &self.forks
+    }
+
+    pub fn genesis_validators_root(&self) -> Node {
+        self.genesis_validators_root
+    }
+}
+
+// The config and preset files are flat maps of `KEY: value` with comments, so they are parsed directly
+// rather than pulling in a YAML implementation
+fn parse_flat_yaml(document: &str, values: &mut BTreeMap<String, String>) {
+    for line in document.lines() {
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim().trim_matches(|c| c == '\'' || c == '"');
+            if !value.is_empty() {
+                values.insert(key.trim().to_string(), value.to_string());
+            }
+        }
+    }
+}
+
+fn optional_number<T: core::str::FromStr>(
+    values: &BTreeMap<String, String>,
+    key: &str,
+) -> Result<Option<T>, ConfigError> {
+    values
+        .get(key)
+        .map(|value| {
+            value.parse().map_err(|_| ConfigError::InvalidValue {
+                key: key.to_string(),
+                value: value.clone(),
+            })
+        })
+        .transpose()
+}
+
+fn number<T: core::str::FromStr>(
+    values: &BTreeMap<String, String>,
+    key: &'static str,
+) -> Result<T, ConfigError> {
+    optional_number(values, key)?.ok_or(ConfigError::MissingKey(key))
+}
+
+fn version(values: &BTreeMap<String, String>, key: &str) -> Result<Option<[u8; 4]>, ConfigError> {
+    let Some(value) = values.get(key) else {
+        return Ok(None);
+    };
+    let invalid = || ConfigError::InvalidValue {
+        key: key.to_string(),
+        value: value.clone(),
+    };
+    let digits = value.strip_prefix("0x").ok_or_else(invalid)?;
+    if digits.len() != 8 {
+        return Err(invalid());
+    }
+    let mut version = [0_u8; 4];
+    for (i, byte) in version.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(Some(version))
+}
+
+// set once so that accessors like `fork_schedule` can hand out static references
+static CONFIG: OnceLock<ChainConfig> = OnceLock::new();
+
+/// A [`Spec`] whose parameters come from the [`ChainConfig`] installed with [`RuntimeSpec::install`].
+/// Every accessor is overridden so the type level parameters below are never read.
+/// Using it before a config is installed panics
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct RuntimeSpec;
+
+impl RuntimeSpec {
+    /// Set the process wide config used by RuntimeSpec. This is done once at startup,
+    /// installing a second config is rejected and leaves the first one in place
+    pub fn install(config: ChainConfig) -> Result<(), ConfigError> {
+        CONFIG
+            .set(config)
+            .map_err(|_| ConfigError::AlreadyInstalled)
+    }
+
+    pub fn config() -> &'static ChainConfig {
+        CONFIG
+            .get()
+            .expect("RuntimeSpec used before a ChainConfig was installed")
+    }
+}
+
+impl Spec for RuntimeSpec {
+    type SlotsPerEpoch = U32;
+    type MaxCommitteesPerSlot = U64;
+    type TargetCommitteeSize = U128;
+    type ShuffleRoundCount = U90;
+    type DomainBeaconAttester = U1;
+    type MinSeedLookahead = U1;
+    type MaxSeedLookahead = U4;
+    type EpochsPerHistoricalVector = U65536;
+    type MinPerEpochChurnLimit = U4;
+    type ChurnLimitQuotient = U65536;
+    type MaxDeposits = U16;
+    type MaxEffectiveBalance = Prod<U32, Exp<U10, U9>>;
+    type EffectiveBalanceIncrement = Exp<U10, U9>;
+    type SlotsPerHistoricalRoot = U8192;
+
+    fn slots_per_epoch() -> usize {
+        Self::config().slots_per_epoch()
+    }
+
+    fn max_committees_per_slot() -> usize {
+        Self::config().max_committees_per_slot()
+    }
+
+    fn target_committee_size() -> usize {
+        Self::config().target_committee_size()
+    }
+
+    fn shuffle_count_count() -> u8 {
+        Self::config().shuffle_count_count()
+    }
+
+    fn fork_schedule() -> &'static [Fork] {
+        Self::config().fork_schedule()
+    }
+
+    fn genesis_validators_root() -> Node {
+        Self::config().genesis_validators_root()
+    }
+
+    fn min_seed_lookahead() -> usize {
+        Self::config().min_seed_lookahead()
+    }
+
+    fn max_seed_lookahead() -> usize {
+        Self::config().max_seed_lookahead()
+    }
+
+    fn epochs_per_historical_vector() -> usize {
+        Self::config().epochs_per_historical_vector()
+    }
+
+    fn domain_beacon_attester() -> u32 {
+        Self::config().domain_beacon_attester()
+    }
+
+    fn min_per_epoch_churn_limit() -> u32 {
+        Self::config().min_per_epoch_churn_limit()
+    }
+
+    fn churn_limit_quotient() -> u32 {
+        Self::config().churn_limit_quotient()
+    }
+
+    fn max_deposits() -> u32 {
+        Self::config().max_deposits()
+    }
+
+    fn max_effective_balance() -> u64 {
+        Self::config().max_effective_balance()
+    }
+
+    fn effective_balance_increment() -> u64 {
+        Self::config().effective_balance_increment()
+    }
+
+    fn slots_per_historical_root() -> usize {
+        Self::config().slots_per_historical_root()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MainnetSpec;
+
+    const MAINNET_CONFIG: &str = "
+# Mainnet config
+PRESET_BASE: 'mainnet'
+CONFIG_NAME: 'mainnet'
+
+# Genesis
+GENESIS_FORK_VERSION: 0x00000000
+# Forking
+ALTAIR_FORK_VERSION: 0x01000000
+ALTAIR_FORK_EPOCH: 74240  # Oct 27, 2021, 10:56:23am UTC
+BELLATRIX_FORK_VERSION: 0x02000000
+BELLATRIX_FORK_EPOCH: 144896
+CAPELLA_FORK_VERSION: 0x03000000
+CAPELLA_FORK_EPOCH: 194048
+DENEB_FORK_VERSION: 0x04000000
+DENEB_FORK_EPOCH: 269568
+ELECTRA_FORK_VERSION: 0x05000000
+ELECTRA_FORK_EPOCH: 364032
+FULU_FORK_VERSION: 0x06000000
+FULU_FORK_EPOCH: 411392
+
+# Validator cycle
+MIN_PER_EPOCH_CHURN_LIMIT: 4
+CHURN_LIMIT_QUOTIENT: 65536
+";
+
+    const MAINNET_PHASE0_PRESET: &str = "
+# Misc
+MAX_COMMITTEES_PER_SLOT: 64
+TARGET_COMMITTEE_SIZE: 128
+SHUFFLE_ROUND_COUNT: 90
+# Gwei values
+EFFECTIVE_BALANCE_INCREMENT: 1000000000
+MAX_EFFECTIVE_BALANCE: 32000000000
+# Time parameters
+MIN_SEED_LOOKAHEAD: 1
+MAX_SEED_LOOKAHEAD: 4
+SLOTS_PER_EPOCH: 32
+# State list lengths
+EPOCHS_PER_HISTORICAL_VECTOR: 65536
+SLOTS_PER_HISTORICAL_ROOT: 8192
+# Max operations per block
+MAX_DEPOSITS: 16
+";
+
+    #[test]
+    fn mainnet_yaml_matches_mainnet_spec() {
+        let config = ChainConfig::from_yaml(
+            &[MAINNET_CONFIG, MAINNET_PHASE0_PRESET],
+            MainnetSpec::genesis_validators_root(),
+        )
+        .unwrap();
+        assert_eq!(config, ChainConfig::from_spec::<MainnetSpec>());
+    }
+
+    #[test]
+    fn missing_and_invalid_values_are_rejected() {
+        let gvr = MainnetSpec::genesis_validators_root();
+        assert_eq!(
+            ChainConfig::from_yaml(&[MAINNET_CONFIG], gvr),
+            Err(ConfigError::MissingKey("SLOTS_PER_EPOCH"))
+        );
+        let bad_version = MAINNET_CONFIG.replace("0x01000000", "0x010000");
+        assert!(matches!(
+            ChainConfig::from_yaml(&[&bad_version, MAINNET_PHASE0_PRESET], gvr),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        let unordered = MAINNET_CONFIG.replace("DENEB_FORK_EPOCH: 269568", "DENEB_FORK_EPOCH: 1");
+        assert_eq!(
+            ChainConfig::from_yaml(&[&unordered, MAINNET_PHASE0_PRESET], gvr),
+            Err(ConfigError::UnorderedForks)
+        );
+    }
+
+    // the only test in this crate that installs a config, so no other test can observe it
+    #[test]
+    fn config_can_only_be_installed_once() {
+        assert_eq!(
+            RuntimeSpec::install(ChainConfig::from_spec::<MainnetSpec>()),
+            Ok(())
+        );
+        assert_eq!(
+            RuntimeSpec::install(ChainConfig::from_spec::<crate::GnosisSpec>()),
+            Err(ConfigError::AlreadyInstalled)
+        );
+        assert_eq!(
+            RuntimeSpec::config(),
+            &ChainConfig::from_spec::<MainnetSpec>()
+        );
+        assert_eq!(
+            RuntimeSpec::slots_per_epoch(),
+            MainnetSpec::slots_per_epoch()
+        );
+    }
+}
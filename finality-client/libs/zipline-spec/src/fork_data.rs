@@ -1,10 +1,48 @@
+use crate::gindex::ContainerLayout;
 use ssz_rs::prelude::*;
 
 type Version = [u8; 4];
 pub type Root = Node;
 use alloc::{vec, vec::Vec};
 #[derive(Default, Debug, SimpleSerialize)]
-pub struct ForkData {
+pub(crate) struct ForkData {
     pub current_version: Version,
     pub genesis_validators_root: Root,
 }
+
+// depth of the BeaconState container tree that the gindex constants in each Spec are written for.
+// Phase0 through Deneb all have between 17 and 32 fields. Electra and later have more than 32
+pub const BASE_STATE_TREE_DEPTH: u32 = crate::gindex::PHASE0_BEACON_STATE.depth();
+
+/// A fork in the schedule of a chain. Each fork applies from its epoch until the epoch of the next one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fork {
+    pub epoch: u64,
+    pub version: [u8; 4],
+    // depth of the BeaconState tree. This changes when a fork takes the number of fields past a power of two
+    pub state_tree_depth: u32,
+    // Electra (EIP-7549) moves the committee index of attestations into committee_bits and replaces the churn
+    // limits. The verifier only implements the attestations and churn of the forks before it
+    pub electra_or_later: bool,
+}
+
+impl Fork {
+    /// A fork whose BeaconState has the given layout
+    pub const fn new(epoch: u64, version: [u8; 4], state: ContainerLayout) -> Self {
+        Self {
+            epoch,
+            version,
+            state_tree_depth: state.depth(),
+            // the pending deposits queue was added to the state by Electra
+            electra_or_later: state.contains("pending_deposits"),
+        }
+    }
+}
+
+/// Move a gindex into the BeaconState from a tree of depth BASE_STATE_TREE_DEPTH to one of the given depth.
+/// The fields keep their index so this only inserts zero bits into the path after the root
+pub fn gindex_at_state_depth(gindex: u64, state_tree_depth: u32) -> u64 {
+    debug_assert!(state_tree_depth >= BASE_STATE_TREE_DEPTH);
+    let leading_bit = 1 << (u64::BITS - 1 - gindex.leading_zeros());
+    gindex + leading_bit * ((1 << (state_tree_depth - BASE_STATE_TREE_DEPTH)) - 1)
+}
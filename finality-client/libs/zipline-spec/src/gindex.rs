@@ -0,0 +1,304 @@
+//! Generalized indices computed from SSZ type layouts.
+//!
+//! Containers are described by their field names in declaration order. Everything here is a const fn
+//! so the gindex constants of each Spec can be evaluated at compile time from these layouts.
+
+// VALIDATOR_REGISTRY_LIMIT is the same in every preset
+pub const VALIDATOR_REGISTRY_LIMIT: u64 = 1 << 40;
+
+/// The field names of an SSZ container in declaration order
+#[derive(Clone, Copy, Debug)]
+pub struct ContainerLayout {
+    pub fields: &'static [&'static str],
+}
+
+impl ContainerLayout {
+    pub const fn new(fields: &'static [&'static str]) -> Self {
+        Self { fields }
+    }
+
+    /// Depth of the merkle tree over the fields of the container
+    pub const fn depth(&self) -> u32 {
+        tree_depth(self.fields.len() as u64)
+    }
+
+    /// Gindex of the root of a field relative to the root of the container.
+    /// Fails to compile when used in a const with a field that isn't part of the container
+    pub const fn field(&self, name: &str) -> u64 {
+        let mut i = 0;
+        while i < self.fields.len() {
+            if str_eq(self.fields[i], name) {
+                return (1 << self.depth()) + i as u64;
+            }
+            i += 1;
+        }
+        panic!("field not in container")
+    }
+
+    /// Whether the container has a field with the given name
+    pub const fn contains(&self, name: &str) -> bool {
+        let mut i = 0;
+        while i < self.fields.len() {
+            if str_eq(self.fields[i], name) {
+                return true;
+            }
+            i += 1;
+        }
+        false
+    }
+}
+
+/// Depth of a merkle tree with (at least) the given number of leaves
+pub const fn tree_depth(leaf_count: u64) -> u32 {
+    if leaf_count <= 1 {
+        0
+    } else {
+        u64::BITS - (leaf_count - 1).leading_zeros()
+    }
+}
+
+/// Depth of the subtree below the root of a vector of `length` 32 byte elements
+pub const fn vector_depth(length: u64) -> u32 {
+    tree_depth(length)
+}
+
+/// Depth of the subtree below the root of a list of up to `limit` composite elements.
+/// This includes the level that mixes in the length
+pub const fn list_depth(limit: u64) -> u32 {
+    tree_depth(limit) + 1
+}
+
+/// Gindex of the first leaf of a subtree of the given depth
+pub const fn first_leaf(root: u64, depth: u32) -> u64 {
+    root << depth
+}
+
+/// Gindex of the length of a list relative to the root of its parent
+pub const fn list_length(list_root: u64) -> u64 {
+    list_root * 2 + 1
+}
+
+/// Gindex of `inner` (relative to the node at `outer`) relative to the root of `outer`
+pub const fn concat(outer: u64, inner: u64) -> u64 {
+    let inner_depth = u64::BITS - 1 - inner.leading_zeros();
+    (outer << inner_depth) | (inner ^ (1 << inner_depth))
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+pub const VALIDATOR: ContainerLayout = ContainerLayout::new(&[
+    "pubkey",
+    "withdrawal_credentials",
+    "effective_balance",
+    "slashed",
+    "activation_eligibility_epoch",
+    "activation_epoch",
+    "exit_epoch",
+    "withdrawable_epoch",
+]);
+
+pub const PHASE0_BEACON_STATE: ContainerLayout = ContainerLayout::new(&[
+    "genesis_time",
+    "genesis_validators_root",
+    "slot",
+    "fork",
+    "latest_block_header",
+    "block_roots",
+    "state_roots",
+    "historical_roots",
+    "eth1_data",
+    "eth1_data_votes",
+    "eth1_deposit_index",
+    "validators",
+    "balances",
+    "randao_mixes",
+    "slashings",
+    "previous_epoch_attestations",
+    "current_epoch_attestations",
+    "justification_bits",
+    "previous_justified_checkpoint",
+    "current_justified_checkpoint",
+    "finalized_checkpoint",
+]);
+
+pub const ALTAIR_BEACON_STATE: ContainerLayout = ContainerLayout::new(&[
+    "genesis_time",
+    "genesis_validators_root",
+    "slot",
+    "fork",
+    "latest_block_header",
+    "block_roots",
+    "state_roots",
+    "historical_roots",
+    "eth1_data",
+    "eth1_data_votes",
+    "eth1_deposit_index",
+    "validators",
+    "balances",
+    "randao_mixes",
+    "slashings",
+    "previous_epoch_participation",
+    "current_epoch_participation",
+    "justification_bits",
+    "previous_justified_checkpoint",
+    "current_justified_checkpoint",
+    "finalized_checkpoint",
+    "inactivity_scores",
+    "current_sync_committee",
+    "next_sync_committee",
+]);
+
+pub const BELLATRIX_BEACON_STATE: ContainerLayout = ContainerLayout::new(&[
+    "genesis_time",
+    "genesis_validators_root",
+    "slot",
+    "fork",
+    "latest_block_header",
+    "block_roots",
+    "state_roots",
+    "historical_roots",
+    "eth1_data",
+    "eth1_data_votes",
+    "eth1_deposit_index",
+    "validators",
+    "balances",
+    "randao_mixes",
+    "slashings",
+    "previous_epoch_participation",
+    "current_epoch_participation",
+    "justification_bits",
+    "previous_justified_checkpoint",
+    "current_justified_checkpoint",
+    "finalized_checkpoint",
+    "inactivity_scores",
+    "current_sync_committee",
+    "next_sync_committee",
+    "latest_execution_payload_header",
+]);
+
+// Deneb only changes the execution payload header so shares this layout
+pub const CAPELLA_BEACON_STATE: ContainerLayout = ContainerLayout::new(&[
+    "genesis_time",
+    "genesis_validators_root",
+    "slot",
+    "fork",
+    "latest_block_header",
+    "block_roots",
+    "state_roots",
+    "historical_roots",
+    "eth1_data",
+    "eth1_data_votes",
+    "eth1_deposit_index",
+    "validators",
+    "balances",
+    "randao_mixes",
+    "slashings",
+    "previous_epoch_participation",
+    "current_epoch_participation",
+    "justification_bits",
+    "previous_justified_checkpoint",
+    "current_justified_checkpoint",
+    "finalized_checkpoint",
+    "inactivity_scores",
+    "current_sync_committee",
+    "next_sync_committee",
+    "latest_execution_payload_header",
+    "next_withdrawal_index",
+    "next_withdrawal_validator_index",
+    "historical_summaries",
+]);
+
+// Electra takes the state past 32 fields so its tree is one level deeper
+pub const ELECTRA_BEACON_STATE: ContainerLayout = ContainerLayout::new(&[
+    "genesis_time",
+    "genesis_validators_root",
+    "slot",
+    "fork",
+    "latest_block_header",
+    "block_roots",
+    "state_roots",
+    "historical_roots",
+    "eth1_data",
+    "eth1_data_votes",
+    "eth1_deposit_index",
+    "validators",
+    "balances",
+    "randao_mixes",
+    "slashings",
+    "previous_epoch_participation",
+    "current_epoch_participation",
+    "justification_bits",
+    "previous_justified_checkpoint",
+    "current_justified_checkpoint",
+    "finalized_checkpoint",
+    "inactivity_scores",
+    "current_sync_committee",
+    "next_sync_committee",
+    "latest_execution_payload_header",
+    "next_withdrawal_index",
+    "next_withdrawal_validator_index",
+    "historical_summaries",
+    "deposit_requests_start_index",
+    "deposit_balance_to_consume",
+    "exit_balance_to_consume",
+    "earliest_exit_epoch",
+    "consolidation_balance_to_consume",
+    "earliest_consolidation_epoch",
+    "pending_deposits",
+    "pending_partial_withdrawals",
+    "pending_consolidations",
+]);
+
+pub const FULU_BEACON_STATE: ContainerLayout = ContainerLayout::new(&[
+    "genesis_time",
+    "genesis_validators_root",
+    "slot",
+    "fork",
+    "latest_block_header",
+    "block_roots",
+    "state_roots",
+    "historical_roots",
+    "eth1_data",
+    "eth1_data_votes",
+    "eth1_deposit_index",
+    "validators",
+    "balances",
+    "randao_mixes",
+    "slashings",
+    "previous_epoch_participation",
+    "current_epoch_participation",
+    "justification_bits",
+    "previous_justified_checkpoint",
+    "current_justified_checkpoint",
+    "finalized_checkpoint",
+    "inactivity_scores",
+    "current_sync_committee",
+    "next_sync_committee",
+    "latest_execution_payload_header",
+    "next_withdrawal_index",
+    "next_withdrawal_validator_index",
+    "historical_summaries",
+    "deposit_requests_start_index",
+    "deposit_balance_to_consume",
+    "exit_balance_to_consume",
+    "earliest_exit_epoch",
+    "consolidation_balance_to_consume",
+    "earliest_consolidation_epoch",
+    "pending_deposits",
+    "pending_partial_withdrawals",
+    "pending_consolidations",
+    "proposer_lookahead",
+]);
@@ -1,15 +1,26 @@
 #![no_std]
 
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 use core::fmt::Debug;
 use typenum::{
-    Exp, Prod, Shleft, Sum, Unsigned, U1, U10, U128, U13, U14, U16, U2, U24, U3, U32, U4, U41, U43,
-    U45, U49, U5, U50, U51, U52, U64, U65536, U8, U9, U90,
+    Exp, Prod, Unsigned, U1, U10, U128, U16, U2, U32, U4, U4096, U64, U65536, U8192, U9, U90,
 };
 
+#[cfg(feature = "std")]
+mod chain_config;
+#[cfg(feature = "std")]
+pub use chain_config::{ChainConfig, ConfigError, RuntimeSpec};
 mod fork_data;
+pub mod gindex;
 use fork_data::ForkData;
+pub use fork_data::{gindex_at_state_depth, Fork, BASE_STATE_TREE_DEPTH};
+use gindex::{
+    ALTAIR_BEACON_STATE, BELLATRIX_BEACON_STATE, CAPELLA_BEACON_STATE, ELECTRA_BEACON_STATE,
+    FULU_BEACON_STATE, PHASE0_BEACON_STATE, VALIDATOR,
+};
 use hex_literal::hex;
 use ssz_rs::prelude::*;
 
@@ -24,54 +35,69 @@ pub trait Spec: 'static + Default + Debug {
 
     // signing domain types
     type DomainBeaconAttester: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-    type ForkVersion: Unsigned + Clone + Sync + Send + Debug + PartialEq;
 
     type MinSeedLookahead: Unsigned + Clone + Sync + Send + Debug + PartialEq;
+    type MaxSeedLookahead: Unsigned + Clone + Sync + Send + Debug + PartialEq;
     type EpochsPerHistoricalVector: Unsigned + Clone + Sync + Send + Debug + PartialEq;
 
     type MinPerEpochChurnLimit: Unsigned + Clone + Sync + Send + Debug + PartialEq;
     type ChurnLimitQuotient: Unsigned + Clone + Sync + Send + Debug + PartialEq;
     type MaxDeposits: Unsigned + Clone + Sync + Send + Debug + PartialEq;
+    type MaxEffectiveBalance: Unsigned + Clone + Sync + Send + Debug + PartialEq;
+    type EffectiveBalanceIncrement: Unsigned + Clone + Sync + Send + Debug + PartialEq;
+    type SlotsPerHistoricalRoot: Unsigned + Clone + Sync + Send + Debug + PartialEq;
     // --- Gindex Constants ---
+    // derived from the phase0 BeaconState and Validator layouts. The fields used here keep their index in
+    // later forks, which only ever append fields. See `state_gindex` for forks that deepen the state tree
+
+    const SLOT_GINDEX: u64 = PHASE0_BEACON_STATE.field("slot");
+
+    const BLOCK_ROOTS_ROOT_GINDEX: u64 = PHASE0_BEACON_STATE.field("block_roots");
 
-    // ssz.phase0.BeaconState.getPathInfo(['validators'])
-    type ValidatorsRootGindex: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-    // ssz.phase0.BeaconState.fields.validators.depth
-    type ValidatorsTreeDepth: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-    // BigInt(2 ** VALIDATORS_TREE_DEPTH) * VALIDATORS_ROOT_GINDEX;
+    const VALIDATORS_ROOT_GINDEX: u64 = PHASE0_BEACON_STATE.field("validators");
+    const VALIDATORS_TREE_DEPTH: u32 = gindex::list_depth(gindex::VALIDATOR_REGISTRY_LIMIT);
     // Index of Validators[0]
-    type Validators0Gindex: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-    // ssz.phase0.ValidatorContainer.getPathInfo(['activationEpoch']).gindex
-    type ActivationEpochGindex: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-    // ssz.phase0.ValidatorContainer.getPathInfo(['exitEpoch']).gindex
-    type ExitEpochGindex: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-    // ssz.phase0.ValidatorContainer.getPathInfo(['pubkey']).gindex
-    type PubkeyGindex: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-    // ssz.phase0.ValidatorContainer.getPathInfo(['effectiveBalance']).gindex
-    type EffectiveBalanceGindex: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-
-    type ValidatorsLengthGindex: Unsigned + Clone + Sync + Send + Debug + PartialEq; //= VALIDATORS_ROOT_GINDEX * 2n + 1n;
-
-    // ssz.phase0.BeaconState.getPathInfo(['justificationBits']).gindex
-    type JustificationBitsGindex: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-
-    // ssz.phase0.BeaconState.getPathInfo(['previousJustifiedCheckpoint']).gindex
-    type PreviousJustifiedCheckpointGindex: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-
-    // ssz.phase0.BeaconState.getPathInfo(['currentJustifiedCheckpoint']).gindex
-    type CurrentJustifiedCheckpointGindex: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-
-    // ssz.phase0.BeaconState.getPathInfo(['finalizedCheckpoint']).gindex
-    type FinalizedCheckpointGindex: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-
-    // ssz.phase0.BeaconState.getPathInfo(['randaoMixes']).gindex
-    type RandaoMixesRootGindex: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-    // ssz.phase0.BeaconState.fields.randaoMixes.depth
-    type RandaoMixesDepth: Unsigned + Clone + Sync + Send + Debug + PartialEq;
-    // ssz.phase0.BeaconState.getPathInfo(['randaoMixes', 0]).gindex
-    // OR
-    // BigInt(2 ** RANDAO_MIXES_DEPTH) * RANDAO_MIXES_ROOT_GINDEX
-    type RandaoMixes0Gindex: Unsigned + Clone + Sync + Send + Debug + PartialEq;
+    const VALIDATORS_0_GINDEX: u64 =
+        gindex::first_leaf(Self::VALIDATORS_ROOT_GINDEX, Self::VALIDATORS_TREE_DEPTH);
+    const VALIDATORS_LENGTH_GINDEX: u64 = gindex::list_length(Self::VALIDATORS_ROOT_GINDEX);
+
+    // relative to the root of a validator
+    const PUBKEY_GINDEX: u64 = VALIDATOR.field("pubkey");
+    const EFFECTIVE_BALANCE_GINDEX: u64 = VALIDATOR.field("effective_balance");
+    const SLASHED_GINDEX: u64 = VALIDATOR.field("slashed");
+    const ACTIVATION_ELIGIBILITY_EPOCH_GINDEX: u64 =
+        VALIDATOR.field("activation_eligibility_epoch");
+    const ACTIVATION_EPOCH_GINDEX: u64 = VALIDATOR.field("activation_epoch");
+    const EXIT_EPOCH_GINDEX: u64 = VALIDATOR.field("exit_epoch");
+
+    const JUSTIFICATION_BITS_GINDEX: u64 = PHASE0_BEACON_STATE.field("justification_bits");
+    const PREVIOUS_JUSTIFIED_CHECKPOINT_GINDEX: u64 =
+        PHASE0_BEACON_STATE.field("previous_justified_checkpoint");
+    const CURRENT_JUSTIFIED_CHECKPOINT_GINDEX: u64 =
+        PHASE0_BEACON_STATE.field("current_justified_checkpoint");
+    const FINALIZED_CHECKPOINT_GINDEX: u64 = PHASE0_BEACON_STATE.field("finalized_checkpoint");
+
+    const RANDAO_MIXES_ROOT_GINDEX: u64 = PHASE0_BEACON_STATE.field("randao_mixes");
+
+    // the depth of the history vectors depends on the preset so these are functions rather than constants
+
+    fn block_roots_depth() -> u32 {
+        gindex::vector_depth(Self::slots_per_historical_root() as u64)
+    }
+
+    // Index of BlockRoots[0]
+    fn block_roots_0_gindex() -> u64 {
+        gindex::first_leaf(Self::BLOCK_ROOTS_ROOT_GINDEX, Self::block_roots_depth())
+    }
+
+    fn randao_mixes_depth() -> u32 {
+        gindex::vector_depth(Self::epochs_per_historical_vector() as u64)
+    }
+
+    // Index of RandaoMixes[0]
+    fn randao_mixes_0_gindex() -> u64 {
+        gindex::first_leaf(Self::RANDAO_MIXES_ROOT_GINDEX, Self::randao_mixes_depth())
+    }
 
     fn slots_per_epoch() -> usize {
         Self::SlotsPerEpoch::to_usize()
@@ -89,25 +115,48 @@ pub trait Spec: 'static + Default + Debug {
         Self::ShuffleRoundCount::to_u8()
     }
 
-    fn fork_version() -> [u8; 4] {
-        // [3, 0, 0, 0] //capella
-        Self::ForkVersion::to_u32().to_be_bytes()
+    // the forks of the chain in ascending epoch order. The first must be at epoch 0
+    fn fork_schedule() -> &'static [Fork];
+
+    fn fork_at_epoch(epoch: u64) -> Fork {
+        let forks = Self::fork_schedule();
+        forks
+            .iter()
+            .rev()
+            .find(|fork| fork.epoch <= epoch)
+            .copied()
+            .unwrap_or(forks[0])
+    }
+
+    fn fork_version(epoch: u64) -> [u8; 4] {
+        Self::fork_at_epoch(epoch).version
+    }
+
+    // the gindex constants are for a state tree of depth BASE_STATE_TREE_DEPTH. This gives the gindex
+    // in the state of the given epoch
+    fn state_gindex(gindex: u64, epoch: u64) -> u64 {
+        gindex_at_state_depth(gindex, Self::fork_at_epoch(epoch).state_tree_depth)
     }
 
     fn genesis_validators_root() -> ssz_rs::Node;
 
-    fn fork_data_root(genesis_validators_root: ssz_rs::Node) -> ssz_rs::Node {
+    fn fork_data_root(
+        current_version: [u8; 4],
+        genesis_validators_root: ssz_rs::Node,
+    ) -> ssz_rs::Node {
         ForkData {
-            current_version: Self::fork_version(),
+            current_version,
             genesis_validators_root,
         }
         .hash_tree_root()
         .unwrap()
     }
 
-    fn beacon_attester_signing_domain() -> [u8; 32] {
-        let domain_type = Self::DomainBeaconAttester::to_u32().to_le_bytes();
-        let fork_data_root = Self::fork_data_root(Self::genesis_validators_root());
+    // domain for attestations with a target in the given epoch. Signatures always use the fork of their target epoch
+    fn beacon_attester_signing_domain(epoch: u64) -> [u8; 32] {
+        let domain_type = Self::domain_beacon_attester().to_le_bytes();
+        let fork_data_root =
+            Self::fork_data_root(Self::fork_version(epoch), Self::genesis_validators_root());
         let mut domain = [0_u8; 32];
         domain[..4].copy_from_slice(&domain_type);
         domain[4..].copy_from_slice(&fork_data_root.as_ref()[..28]);
@@ -118,6 +167,11 @@ pub trait Spec: 'static + Default + Debug {
         Self::MinSeedLookahead::to_usize()
     }
 
+    // activations and exits processed in an epoch take effect max_seed_lookahead + 1 epochs later
+    fn max_seed_lookahead() -> usize {
+        Self::MaxSeedLookahead::to_usize()
+    }
+
     fn epochs_per_historical_vector() -> usize {
         Self::EpochsPerHistoricalVector::to_usize()
     }
@@ -143,7 +197,7 @@ pub trait Spec: 'static + Default + Debug {
         max_committees_per_slot: usize,
         target_committee_size: usize,
     ) -> usize {
-        let slots_per_epoch = Self::SlotsPerEpoch::to_usize();
+        let slots_per_epoch = Self::slots_per_epoch();
 
         core::cmp::max(
             1,
@@ -169,8 +223,30 @@ pub trait Spec: 'static + Default + Debug {
     fn max_deposits() -> u32 {
         Self::MaxDeposits::to_u32()
     }
+
+    fn max_effective_balance() -> u64 {
+        Self::MaxEffectiveBalance::to_u64()
+    }
+
+    fn effective_balance_increment() -> u64 {
+        Self::EffectiveBalanceIncrement::to_u64()
+    }
+
+    fn slots_per_historical_root() -> usize {
+        Self::SlotsPerHistoricalRoot::to_usize()
+    }
 }
 
+const MAINNET_FORKS: &[Fork] = &[
+    Fork::new(0, [0, 0, 0, 0], PHASE0_BEACON_STATE), // phase0
+    Fork::new(74240, [1, 0, 0, 0], ALTAIR_BEACON_STATE), // altair
+    Fork::new(144896, [2, 0, 0, 0], BELLATRIX_BEACON_STATE), // bellatrix
+    Fork::new(194048, [3, 0, 0, 0], CAPELLA_BEACON_STATE), // capella
+    Fork::new(269568, [4, 0, 0, 0], CAPELLA_BEACON_STATE), // deneb
+    Fork::new(364032, [5, 0, 0, 0], ELECTRA_BEACON_STATE), // electra
+    Fork::new(411392, [6, 0, 0, 0], FULU_BEACON_STATE), // fulu
+];
+
 /// Ethereum Foundation specifications.
 #[derive(Clone, PartialEq, Eq, Debug, Default)]
 pub struct MainnetSpec;
@@ -182,45 +258,21 @@ impl Spec for MainnetSpec {
     type ShuffleRoundCount = U90;
 
     type DomainBeaconAttester = U1;
-    type ForkVersion = Shleft<U3, U24>; // capella big endian [3, 0, 0, 0]
 
     type MinSeedLookahead = U1;
+    type MaxSeedLookahead = U4;
     type EpochsPerHistoricalVector = U65536;
 
     type MinPerEpochChurnLimit = U4;
     type ChurnLimitQuotient = U65536;
     type MaxDeposits = U16;
-    // --- Gindex Constants ---
-    // 43
-    type ValidatorsRootGindex = U43;
-    // 41
-    type ValidatorsTreeDepth = U41;
-    // 94557999988736
-    type Validators0Gindex = Prod<Exp<U2, U41>, U43>;
-    // 13
-    type ActivationEpochGindex = U13;
-    // 14
-    type ExitEpochGindex = U14;
-    // 8
-    type PubkeyGindex = U8;
-    // 10
-    type EffectiveBalanceGindex = U10;
-    // 87
-    type ValidatorsLengthGindex = Sum<Prod<Self::ValidatorsRootGindex, U2>, U1>;
-    // 49
-    type JustificationBitsGindex = U49;
-    // 50
-    type PreviousJustifiedCheckpointGindex = U50;
-    // 51
-    type CurrentJustifiedCheckpointGindex = U51;
-    // 52
-    type FinalizedCheckpointGindex = U52;
-    // 45
-    type RandaoMixesRootGindex = U45;
-    // 16
-    type RandaoMixesDepth = U16;
-    // 2949120
-    type RandaoMixes0Gindex = Prod<Prod<Exp<U2, U16>, U9>, U5>;
+    type MaxEffectiveBalance = Prod<U32, Exp<U10, U9>>; // 32 ETH in Gwei
+    type EffectiveBalanceIncrement = Exp<U10, U9>; // 1 ETH in Gwei
+    type SlotsPerHistoricalRoot = U8192;
+
+    fn fork_schedule() -> &'static [Fork] {
+        MAINNET_FORKS
+    }
 
     fn genesis_validators_root() -> ssz_rs::Node {
         Node::try_from(
@@ -230,6 +282,187 @@ impl Spec for MainnetSpec {
     }
 }
 
+const GNOSIS_FORKS: &[Fork] = &[
+    Fork::new(0, [0x00, 0x00, 0x00, 0x64], PHASE0_BEACON_STATE), // phase0
+    Fork::new(512, [0x01, 0x00, 0x00, 0x64], ALTAIR_BEACON_STATE), // altair
+    Fork::new(385536, [0x02, 0x00, 0x00, 0x64], BELLATRIX_BEACON_STATE), // bellatrix
+    Fork::new(648704, [0x03, 0x00, 0x00, 0x64], CAPELLA_BEACON_STATE), // capella
+    Fork::new(889856, [0x04, 0x00, 0x00, 0x64], CAPELLA_BEACON_STATE), // deneb
+    Fork::new(1337856, [0x05, 0x00, 0x00, 0x64], ELECTRA_BEACON_STATE), // electra
+];
+
+/// Gnosis beacon chain. Uses the gnosis preset with 16 slot epochs.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct GnosisSpec;
+
+impl Spec for GnosisSpec {
+    type SlotsPerEpoch = U16;
+    type MaxCommitteesPerSlot = U64;
+    type TargetCommitteeSize = U128;
+    type ShuffleRoundCount = U90;
+
+    type DomainBeaconAttester = U1;
+
+    type MinSeedLookahead = U1;
+    type MaxSeedLookahead = U4;
+    type EpochsPerHistoricalVector = U65536;
+
+    type MinPerEpochChurnLimit = U4;
+    type ChurnLimitQuotient = U4096;
+    type MaxDeposits = U16;
+    type MaxEffectiveBalance = Prod<U32, Exp<U10, U9>>; // 32 mGNO (1 GNO) in Gwei
+    type EffectiveBalanceIncrement = Exp<U10, U9>; // 1 mGNO in Gwei
+    type SlotsPerHistoricalRoot = U8192;
+
+    fn fork_schedule() -> &'static [Fork] {
+        GNOSIS_FORKS
+    }
+
+    fn genesis_validators_root() -> ssz_rs::Node {
+        Node::try_from(
+            hex!("f5dcb5564e829aab27264b9becd5dfaa017085611224cb3036f573368dbb9d47").as_slice(),
+        )
+        .unwrap()
+    }
+}
+
+const CHIADO_FORKS: &[Fork] = &[
+    Fork::new(0, [0x00, 0x00, 0x00, 0x6f], PHASE0_BEACON_STATE), // phase0
+    Fork::new(90, [0x01, 0x00, 0x00, 0x6f], ALTAIR_BEACON_STATE), // altair
+    Fork::new(180, [0x02, 0x00, 0x00, 0x6f], BELLATRIX_BEACON_STATE), // bellatrix
+    Fork::new(244224, [0x03, 0x00, 0x00, 0x6f], CAPELLA_BEACON_STATE), // capella
+    Fork::new(516608, [0x04, 0x00, 0x00, 0x6f], CAPELLA_BEACON_STATE), // deneb
+    Fork::new(948224, [0x05, 0x00, 0x00, 0x6f], ELECTRA_BEACON_STATE), // electra
+];
+
+/// Chiado, the Gnosis testnet. Uses the gnosis preset.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ChiadoSpec;
+
+impl Spec for ChiadoSpec {
+    type SlotsPerEpoch = U16;
+    type MaxCommitteesPerSlot = U64;
+    type TargetCommitteeSize = U128;
+    type ShuffleRoundCount = U90;
+
+    type DomainBeaconAttester = U1;
+
+    type MinSeedLookahead = U1;
+    type MaxSeedLookahead = U4;
+    type EpochsPerHistoricalVector = U65536;
+
+    type MinPerEpochChurnLimit = U2;
+    type ChurnLimitQuotient = U4096;
+    type MaxDeposits = U16;
+    type MaxEffectiveBalance = Prod<U32, Exp<U10, U9>>; // 32 mGNO (1 GNO) in Gwei
+    type EffectiveBalanceIncrement = Exp<U10, U9>; // 1 mGNO in Gwei
+    type SlotsPerHistoricalRoot = U8192;
+
+    fn fork_schedule() -> &'static [Fork] {
+        CHIADO_FORKS
+    }
+
+    fn genesis_validators_root() -> ssz_rs::Node {
+        Node::try_from(
+            hex!("9d642dac73058fbf39c0ae41ab1e34e4d889043cb199851ded7095bc99eb4c1e").as_slice(),
+        )
+        .unwrap()
+    }
+}
+
+const HOLESKY_FORKS: &[Fork] = &[
+    Fork::new(0, [0x01, 0x01, 0x70, 0x00], PHASE0_BEACON_STATE), // phase0
+    Fork::new(0, [0x02, 0x01, 0x70, 0x00], ALTAIR_BEACON_STATE), // altair
+    Fork::new(0, [0x03, 0x01, 0x70, 0x00], BELLATRIX_BEACON_STATE), // bellatrix
+    Fork::new(256, [0x04, 0x01, 0x70, 0x00], CAPELLA_BEACON_STATE), // capella
+    Fork::new(29696, [0x05, 0x01, 0x70, 0x00], CAPELLA_BEACON_STATE), // deneb
+    Fork::new(115968, [0x06, 0x01, 0x70, 0x00], ELECTRA_BEACON_STATE), // electra
+    Fork::new(165120, [0x07, 0x01, 0x70, 0x00], FULU_BEACON_STATE), // fulu
+];
+
+/// Holesky testnet. Uses the mainnet preset.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct HoleskySpec;
+
+impl Spec for HoleskySpec {
+    type SlotsPerEpoch = U32;
+    type MaxCommitteesPerSlot = U64;
+    type TargetCommitteeSize = U128;
+    type ShuffleRoundCount = U90;
+
+    type DomainBeaconAttester = U1;
+
+    type MinSeedLookahead = U1;
+    type MaxSeedLookahead = U4;
+    type EpochsPerHistoricalVector = U65536;
+
+    type MinPerEpochChurnLimit = U4;
+    type ChurnLimitQuotient = U65536;
+    type MaxDeposits = U16;
+    type MaxEffectiveBalance = Prod<U32, Exp<U10, U9>>; // 32 ETH in Gwei
+    type EffectiveBalanceIncrement = Exp<U10, U9>; // 1 ETH in Gwei
+    type SlotsPerHistoricalRoot = U8192;
+
+    fn fork_schedule() -> &'static [Fork] {
+        HOLESKY_FORKS
+    }
+
+    fn genesis_validators_root() -> ssz_rs::Node {
+        Node::try_from(
+            hex!("9143aa7c615a7f7115e2b6aac319c03529df8242ae705fba9df39b79c59fa8b1").as_slice(),
+        )
+        .unwrap()
+    }
+}
+
+const SEPOLIA_FORKS: &[Fork] = &[
+    Fork::new(0, [0x90, 0x00, 0x00, 0x69], PHASE0_BEACON_STATE), // phase0
+    Fork::new(50, [0x90, 0x00, 0x00, 0x70], ALTAIR_BEACON_STATE), // altair
+    Fork::new(100, [0x90, 0x00, 0x00, 0x71], BELLATRIX_BEACON_STATE), // bellatrix
+    Fork::new(56832, [0x90, 0x00, 0x00, 0x72], CAPELLA_BEACON_STATE), // capella
+    Fork::new(132608, [0x90, 0x00, 0x00, 0x73], CAPELLA_BEACON_STATE), // deneb
+    Fork::new(222464, [0x90, 0x00, 0x00, 0x74], ELECTRA_BEACON_STATE), // electra
+    Fork::new(272640, [0x90, 0x00, 0x00, 0x75], FULU_BEACON_STATE), // fulu
+];
+
+/// Sepolia testnet. Uses the mainnet preset.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct SepoliaSpec;
+
+impl Spec for SepoliaSpec {
+    type SlotsPerEpoch = U32;
+    type MaxCommitteesPerSlot = U64;
+    type TargetCommitteeSize = U128;
+    type ShuffleRoundCount = U90;
+
+    type DomainBeaconAttester = U1;
+
+    type MinSeedLookahead = U1;
+    type MaxSeedLookahead = U4;
+    type EpochsPerHistoricalVector = U65536;
+
+    type MinPerEpochChurnLimit = U4;
+    type ChurnLimitQuotient = U65536;
+    type MaxDeposits = U16;
+    type MaxEffectiveBalance = Prod<U32, Exp<U10, U9>>; // 32 ETH in Gwei
+    type EffectiveBalanceIncrement = Exp<U10, U9>; // 1 ETH in Gwei
+    type SlotsPerHistoricalRoot = U8192;
+
+    fn fork_schedule() -> &'static [Fork] {
+        SEPOLIA_FORKS
+    }
+
+    fn genesis_validators_root() -> ssz_rs::Node {
+        Node::try_from(
+            hex!("d8ea171f3c94aea21ebc42a1ed61052acf3f9209c00e4efbaaddac09ed9b8078").as_slice(),
+        )
+        .unwrap()
+    }
+}
+
+// the spec tests start from bellatrix
+const SPEC_TEST_FORKS: &[Fork] = &[Fork::new(0, [2, 0, 0, 0], BELLATRIX_BEACON_STATE)];
+
 /// Ethereum Foundation specifications.
 #[derive(Clone, PartialEq, Eq, Debug, Default)]
 pub struct SpecTestSpec;
@@ -241,45 +474,21 @@ impl Spec for SpecTestSpec {
     type ShuffleRoundCount = U90;
 
     type DomainBeaconAttester = U1;
-    type ForkVersion = Shleft<U2, U24>;
 
     type MinSeedLookahead = U1;
+    type MaxSeedLookahead = U4;
     type EpochsPerHistoricalVector = U65536;
 
     type MinPerEpochChurnLimit = U4;
     type ChurnLimitQuotient = U65536;
     type MaxDeposits = U16;
-    // --- Gindex Constants ---
-    // 43
-    type ValidatorsRootGindex = U43;
-    // 41
-    type ValidatorsTreeDepth = U41;
-    // 94557999988736
-    type Validators0Gindex = Prod<Exp<U2, U41>, U43>;
-    // 13
-    type ActivationEpochGindex = U13;
-    // 14
-    type ExitEpochGindex = U14;
-    // 8
-    type PubkeyGindex = U8;
-    // 10
-    type EffectiveBalanceGindex = U10;
-    // 87
-    type ValidatorsLengthGindex = Sum<Prod<Self::ValidatorsRootGindex, U2>, U1>;
-    // 49
-    type JustificationBitsGindex = U49;
-    // 50
-    type PreviousJustifiedCheckpointGindex = U50;
-    // 51
-    type CurrentJustifiedCheckpointGindex = U51;
-    // 52
-    type FinalizedCheckpointGindex = U52;
-    // 45
-    type RandaoMixesRootGindex = U45;
-    // 16
-    type RandaoMixesDepth = U16;
-    // 2949120
-    type RandaoMixes0Gindex = Prod<Prod<Exp<U2, U16>, U9>, U5>;
+    type MaxEffectiveBalance = Prod<U32, Exp<U10, U9>>; // 32 ETH in Gwei
+    type EffectiveBalanceIncrement = Exp<U10, U9>; // 1 ETH in Gwei
+    type SlotsPerHistoricalRoot = U8192;
+
+    fn fork_schedule() -> &'static [Fork] {
+        SPEC_TEST_FORKS
+    }
 
     fn genesis_validators_root() -> ssz_rs::Node {
         // find this in a state object from the chain
@@ -292,6 +501,9 @@ impl Spec for SpecTestSpec {
     }
 }
 
+// bellatrix minimal
+const MINIMAL_FORKS: &[Fork] = &[Fork::new(0, [2, 0, 0, 1], BELLATRIX_BEACON_STATE)];
+
 /// Ethereum Foundation minimal spec, as defined in the eth2.0-specs repo.
 #[derive(Clone, PartialEq, Eq, Debug, Default)]
 pub struct MinimalSpec;
@@ -303,46 +515,21 @@ impl Spec for MinimalSpec {
     type ShuffleRoundCount = U10;
 
     type DomainBeaconAttester = U1;
-    type ForkVersion = Sum<Shleft<U2, U24>, U1>; // bellatrix minimal big endian [2, 0, 0, 1]
 
     type MinSeedLookahead = U1;
+    type MaxSeedLookahead = U4;
     type EpochsPerHistoricalVector = U64;
 
     type MinPerEpochChurnLimit = U4;
     type ChurnLimitQuotient = U32;
     type MaxDeposits = U16;
-    // --- Gindex Constants ---
-    // 43
-    type ValidatorsRootGindex = U43;
-    // 41
-    type ValidatorsTreeDepth = U41;
-    // 94557999988736
-    type Validators0Gindex = Prod<Exp<U2, U41>, U43>;
-    // 13
-    type ActivationEpochGindex = U13;
-    // 14
-    type ExitEpochGindex = U14;
-    // 8
-    type PubkeyGindex = U8;
-    // 10
-    type EffectiveBalanceGindex = U10;
-    // 87
-    type ValidatorsLengthGindex = Sum<Prod<Self::ValidatorsRootGindex, U2>, U1>;
-    // 49
-    type JustificationBitsGindex = U49;
-    // 50
-    type PreviousJustifiedCheckpointGindex = U50;
-    // 51
-    type CurrentJustifiedCheckpointGindex = U51;
-    // 52
-    type FinalizedCheckpointGindex = U52;
-    // 45
-    type RandaoMixesRootGindex = U45;
-    // 16
-    type RandaoMixesDepth = U16;
-    // 2949120
-    type RandaoMixes0Gindex = Prod<Prod<Exp<U2, U16>, U9>, U5>;
-    // TODO: Add other fields we might need
+    type MaxEffectiveBalance = Prod<U32, Exp<U10, U9>>; // 32 ETH in Gwei
+    type EffectiveBalanceIncrement = Exp<U10, U9>; // 1 ETH in Gwei
+    type SlotsPerHistoricalRoot = U64;
+
+    fn fork_schedule() -> &'static [Fork] {
+        MINIMAL_FORKS
+    }
 
     fn genesis_validators_root() -> ssz_rs::Node {
         Node::try_from(
@@ -351,3 +538,121 @@ impl Spec for MinimalSpec {
         .unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fork_version_uses_latest_scheduled_fork() {
+        assert_eq!(MainnetSpec::fork_version(0), [0, 0, 0, 0]);
+        assert_eq!(MainnetSpec::fork_version(194047), [2, 0, 0, 0]);
+        assert_eq!(MainnetSpec::fork_version(194048), [3, 0, 0, 0]);
+        assert_eq!(MainnetSpec::fork_version(364031), [4, 0, 0, 0]);
+        assert_eq!(MainnetSpec::fork_version(364032), [5, 0, 0, 0]);
+        assert_eq!(MainnetSpec::fork_version(u64::MAX), [6, 0, 0, 0]);
+        assert_ne!(
+            MainnetSpec::beacon_attester_signing_domain(194047),
+            MainnetSpec::beacon_attester_signing_domain(194048)
+        );
+    }
+
+    #[test]
+    fn state_gindex_moves_fields_to_deeper_state() {
+        assert_eq!(gindex_at_state_depth(43, BASE_STATE_TREE_DEPTH), 43);
+        // validators is field 11 so is at 64 + 11 in a state with up to 64 fields
+        assert_eq!(gindex_at_state_depth(43, 6), 75);
+        assert_eq!(
+            gindex_at_state_depth(MainnetSpec::VALIDATORS_0_GINDEX, 6),
+            75 << 41
+        );
+        // the Electra state has more than 32 fields
+        assert_eq!(MainnetSpec::state_gindex(43, 364031), 43);
+        assert_eq!(MainnetSpec::state_gindex(43, 364032), 75);
+    }
+
+    #[test]
+    fn electra_and_later_forks_are_flagged() {
+        assert!(!MainnetSpec::fork_at_epoch(364031).electra_or_later);
+        assert!(MainnetSpec::fork_at_epoch(364032).electra_or_later);
+        assert!(MainnetSpec::fork_at_epoch(u64::MAX).electra_or_later);
+        assert!(!MinimalSpec::fork_at_epoch(u64::MAX).electra_or_later);
+    }
+
+    #[test]
+    fn derived_gindices_match_beacon_state_layout() {
+        assert_eq!(MainnetSpec::SLOT_GINDEX, 34);
+        assert_eq!(MainnetSpec::block_roots_0_gindex(), 303104);
+        assert_eq!(MainnetSpec::VALIDATORS_TREE_DEPTH, 41);
+        assert_eq!(MainnetSpec::VALIDATORS_0_GINDEX, 94557999988736);
+        assert_eq!(MainnetSpec::VALIDATORS_LENGTH_GINDEX, 87);
+        assert_eq!(MainnetSpec::PUBKEY_GINDEX, 8);
+        assert_eq!(MainnetSpec::EFFECTIVE_BALANCE_GINDEX, 10);
+        assert_eq!(MainnetSpec::SLASHED_GINDEX, 11);
+        assert_eq!(MainnetSpec::ACTIVATION_ELIGIBILITY_EPOCH_GINDEX, 12);
+        assert_eq!(MainnetSpec::ACTIVATION_EPOCH_GINDEX, 13);
+        assert_eq!(MainnetSpec::EXIT_EPOCH_GINDEX, 14);
+        assert_eq!(MainnetSpec::JUSTIFICATION_BITS_GINDEX, 49);
+        assert_eq!(MainnetSpec::FINALIZED_CHECKPOINT_GINDEX, 52);
+        assert_eq!(MainnetSpec::randao_mixes_0_gindex(), 2949120);
+        // the minimal preset has shorter history vectors
+        assert_eq!(MinimalSpec::block_roots_0_gindex(), 2368);
+        assert_eq!(MinimalSpec::randao_mixes_0_gindex(), 45 << 6);
+    }
+
+    #[test]
+    fn later_forks_keep_phase0_field_gindices() {
+        let fields = [
+            "slot",
+            "block_roots",
+            "validators",
+            "randao_mixes",
+            "justification_bits",
+            "previous_justified_checkpoint",
+            "current_justified_checkpoint",
+            "finalized_checkpoint",
+        ];
+        for layout in [
+            gindex::ALTAIR_BEACON_STATE,
+            gindex::BELLATRIX_BEACON_STATE,
+            gindex::CAPELLA_BEACON_STATE,
+        ] {
+            assert_eq!(layout.depth(), BASE_STATE_TREE_DEPTH);
+            for field in fields {
+                assert_eq!(layout.field(field), PHASE0_BEACON_STATE.field(field));
+            }
+        }
+        for layout in [gindex::ELECTRA_BEACON_STATE, gindex::FULU_BEACON_STATE] {
+            assert_eq!(layout.depth(), 6);
+            for field in fields {
+                assert_eq!(
+                    layout.field(field),
+                    gindex_at_state_depth(PHASE0_BEACON_STATE.field(field), 6)
+                );
+            }
+        }
+        assert_eq!(gindex::concat(43, 2), 86);
+        assert_eq!(gindex::concat(43, 3), 87);
+    }
+
+    fn assert_schedule_well_formed<S: Spec>() {
+        let forks = S::fork_schedule();
+        assert_eq!(forks[0].epoch, 0);
+        assert!(forks.windows(2).all(|w| w[0].epoch <= w[1].epoch));
+    }
+
+    #[test]
+    fn network_fork_schedules_are_ordered() {
+        assert_schedule_well_formed::<GnosisSpec>();
+        assert_schedule_well_formed::<ChiadoSpec>();
+        assert_schedule_well_formed::<HoleskySpec>();
+        assert_schedule_well_formed::<SepoliaSpec>();
+        assert_eq!(GnosisSpec::fork_version(889856), [4, 0, 0, 100]);
+        assert_eq!(HoleskySpec::fork_version(0), [3, 1, 112, 0]);
+        assert_eq!(SepoliaSpec::fork_version(132608), [0x90, 0, 0, 0x73]);
+        assert_eq!(GnosisSpec::fork_version(1337856), [5, 0, 0, 100]);
+        assert_eq!(ChiadoSpec::fork_version(948224), [5, 0, 0, 111]);
+        assert_eq!(HoleskySpec::fork_version(165120), [7, 1, 112, 0]);
+        assert_eq!(SepoliaSpec::fork_version(272640), [0x90, 0, 0, 0x75]);
+    }
+}
@@ -0,0 +1,131 @@
+//! Active set hints let the input carry the validators active in the trusted epoch and their total effective balance.
+//! These are checked against the trusted state once and then reused for every epoch rather than recomputing the
+//! active validators from the full registry each time they are needed.
+
+use alloc::{vec, vec::Vec};
+use log::trace;
+use ssz_rs::prelude::*;
+
+// far larger than any current registry but small enough that the bitlist can be handled on 32-bit targets
+pub const MAX_HINTED_VALIDATORS: usize = 1 << 24;
+
+pub const FAR_FUTURE_EPOCH: u64 = u64::MAX;
+
+#[derive(Clone, Debug, Default, SimpleSerialize, PartialEq)]
+pub struct ActiveSetHint {
+    pub active_validators: Bitlist<MAX_HINTED_VALIDATORS>, // bit i is set if validator i is active in the trusted epoch
+    pub total_active_balance: u64,
+}
+
+/// Reasons an active set hint can be rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActiveSetHintRejection {
+    ValidatorCount { expected: usize, found: usize },
+    ActivityMismatch { validator_index: usize },
+    TotalActiveBalance { expected: u64, found: u64 },
+}
+
+impl ActiveSetHint {
+    pub fn is_empty(&self) -> bool {
+        self.active_validators.is_empty()
+    }
+}
+
+/// The active validators at some epoch after they have been checked against a hint.
+/// Also records the validators that become active or exit after that epoch so the active
+/// validators in later epochs can be derived without another pass over the registry
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActiveSet {
+    epoch: u64,
+    indices: Vec<usize>,
+    total_active_balance: u64,
+    // (validator_index, activation_epoch, exit_epoch) for validators with a pending activation or exit
+    pending: Vec<(usize, u64, u64)>,
+}
+
+pub fn is_active_validator(activation_epoch: u64, exit_epoch: u64, epoch: u64) -> bool {
+    activation_epoch <= epoch && epoch < exit_epoch
+}
+
+impl ActiveSet {
+    /// Check the hint for the given epoch in a single pass over (activation_epoch, exit_epoch, effective_balance)
+    /// for every validator in the registry
+    pub fn verify(
+        epoch: u64,
+        hint: &ActiveSetHint,
+        validators: impl ExactSizeIterator<Item = (u64, u64, u64)>,
+    ) -> Result<Self, ActiveSetHintRejection> {
+        if validators.len() != hint.active_validators.len() {
+            trace!("active set hint has the wrong number of validators");
+            return Err(ActiveSetHintRejection::ValidatorCount {
+                expected: validators.len(),
+                found: hint.active_validators.len(),
+            });
+        }
+        let mut indices = Vec::new();
+        let mut pending = Vec::new();
+        let mut total_active_balance = 0_u64;
+        for (validator_index, ((activation_epoch, exit_epoch, balance), hinted)) in
+            validators.zip(hint.active_validators.iter()).enumerate()
+        {
+            let active = is_active_validator(activation_epoch, exit_epoch, epoch);
+            if active != *hinted {
+                trace!("active set hint wrong for validator {}", validator_index);
+                return Err(ActiveSetHintRejection::ActivityMismatch { validator_index });
+            }
+            if active {
+                indices.push(validator_index);
+                total_active_balance = total_active_balance.saturating_add(balance);
+            }
+            if (activation_epoch > epoch && activation_epoch != FAR_FUTURE_EPOCH)
+                || (exit_epoch > epoch && exit_epoch != FAR_FUTURE_EPOCH)
+            {
+                pending.push((validator_index, activation_epoch, exit_epoch));
+            }
+        }
+        if total_active_balance != hint.total_active_balance {
+            trace!("active set hint total balance incorrect");
+            return Err(ActiveSetHintRejection::TotalActiveBalance {
+                expected: total_active_balance,
+                found: hint.total_active_balance,
+            });
+        }
+        Ok(Self {
+            epoch,
+            indices,
+            total_active_balance,
+            pending,
+        })
+    }
+
+    /// The active validator indices at the given epoch if they can be derived from this set.
+    /// This is only possible for the same or later epochs
+    pub fn active_validator_indices(&self, epoch: u64) -> Option<Vec<usize>> {
+        if epoch < self.epoch {
+            return None;
+        }
+        if epoch == self.epoch || self.pending.is_empty() {
+            return Some(self.indices.clone());
+        }
+        // validators without a pending activation or exit have the same status in all later epochs
+        let mut indices: Vec<usize> = self
+            .indices
+            .iter()
+            .copied()
+            .filter(|i| self.pending.binary_search_by_key(i, |p| p.0).is_err())
+            .chain(
+                self.pending
+                    .iter()
+                    .filter(|(_, activation, exit)| is_active_validator(*activation, *exit, epoch))
+                    .map(|p| p.0),
+            )
+            .collect();
+        indices.sort_unstable();
+        Some(indices)
+    }
+
+    /// The total active balance if it is known for the given epoch
+    pub fn total_active_balance(&self, epoch: u64) -> Option<u64> {
+        (epoch == self.epoch).then_some(self.total_active_balance)
+    }
+}
@@ -0,0 +1,241 @@
+//! Ancestry proofs allow relaying every block root since the last finalized checkpoint rather than only epoch boundary blocks.
+//! Each block root is proven to be contained in the `block_roots` vector of the state produced by the candidate checkpoint block.
+//! If the candidate is finalized then so are all of its ancestors, so consuming applications can use these roots without
+//! checking ancestry proofs themselves.
+
+use crate::attestation::Checkpoint;
+use alloc::{vec, vec::Vec};
+use crypto::hash::H256;
+use log::trace;
+use ssz_rs::prelude::*;
+use ssz_rs::{is_valid_merkle_branch, Node};
+use zipline_spec::Spec;
+
+pub const MAX_BLOCK_ROOT_PROOFS: usize = 8192; // SLOTS_PER_HISTORICAL_ROOT on mainnet
+pub const MAX_PROOF_DEPTH: usize = 32;
+
+// gindex of state_root in beacon block header
+const STATE_ROOT_IN_HEADER_GINDEX: usize = 11;
+
+#[derive(Clone, Debug, Default, SimpleSerialize, PartialEq)]
+pub struct BlockRootProof {
+    pub slot: u64,
+    pub root: H256,
+    pub proof: List<H256, MAX_PROOF_DEPTH>, // SSZ proof that the root is contained in block_roots of the candidate state
+}
+
+/// Proofs of block roots preceding the candidate checkpoint block.
+///
+/// Only `block_roots` proofs are supported, so every proven slot must be within the SLOTS_PER_HISTORICAL_ROOT slots
+/// before the candidate state. Older roots would need proofs against `historical_roots`/`historical_summaries` and
+/// are rejected with [`AncestryRejection::SlotBeforeBlockRootsWindow`]
+#[derive(Clone, Debug, Default, SimpleSerialize, PartialEq)]
+pub struct CandidateAncestry {
+    pub state_root: H256,           // state root of the candidate_cp block
+    pub state_proof: List<H256, 3>, // SSZ proof that the state root is contained in the candidate_cp block
+    pub slot: u64,                  // slot of the candidate state
+    pub slot_proof: List<H256, MAX_PROOF_DEPTH>, // SSZ proof that the slot is contained in the candidate state
+    pub block_roots: List<BlockRootProof, MAX_BLOCK_ROOT_PROOFS>,
+}
+
+/// Reasons ancestry proofs can be rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum AncestryRejection {
+    InvalidStateProof,
+    InvalidSlotProof,
+    // the slot is not before the candidate state
+    SlotOutOfRange { slot: u64 },
+    // the slot is older than the oldest root still in block_roots of the candidate state
+    SlotBeforeBlockRootsWindow { slot: u64, oldest_slot: u64 },
+    InvalidBlockRootProof { slot: u64 },
+}
+
+impl CandidateAncestry {
+    pub fn validate<S: Spec>(&self, candidate_cp: &Checkpoint) -> Result<(), AncestryRejection> {
+        if self.block_roots.is_empty() {
+            // nothing to prove
+            return Ok(());
+        }
+        if !is_valid_proof(
+            &self.state_root,
+            &self.state_proof,
+            STATE_ROOT_IN_HEADER_GINDEX,
+            &candidate_cp.root,
+        ) {
+            trace!("candidate state root proof invalid");
+            return Err(AncestryRejection::InvalidStateProof);
+        }
+        let mut slot_leaf = [0_u8; 32];
+        slot_leaf[..8].copy_from_slice(&self.slot.to_le_bytes());
+        if !is_valid_proof(
+            &slot_leaf,
+            &self.slot_proof,
+            S::state_gindex(S::SLOT_GINDEX, candidate_cp.epoch) as usize,
+            &self.state_root,
+        ) {
+            trace!("candidate state slot proof invalid");
+            return Err(AncestryRejection::InvalidSlotProof);
+        }
+
+        // block_roots only holds the roots for the SLOTS_PER_HISTORICAL_ROOT slots preceding the state slot
+        let slots_per_historical_root = S::slots_per_historical_root() as u64;
+        let oldest_slot = self.slot.saturating_sub(slots_per_historical_root);
+        for block_root in self.block_roots.iter() {
+            if block_root.slot >= self.slot {
+                trace!("block root slot {} out of range", block_root.slot);
+                return Err(AncestryRejection::SlotOutOfRange {
+                    slot: block_root.slot,
+                });
+            }
+            if block_root.slot < oldest_slot {
+                trace!(
+                    "block root slot {} is before the block_roots window",
+                    block_root.slot
+                );
+                return Err(AncestryRejection::SlotBeforeBlockRootsWindow {
+                    slot: block_root.slot,
+                    oldest_slot,
+                });
+            }
+            let gindex = S::state_gindex(S::block_roots_0_gindex(), candidate_cp.epoch) as usize
+                + (block_root.slot % slots_per_historical_root) as usize;
+            if !is_valid_proof(
+                &block_root.root,
+                &block_root.proof,
+                gindex,
+                &self.state_root,
+            ) {
+                trace!("block root proof for slot {} invalid", block_root.slot);
+                return Err(AncestryRejection::InvalidBlockRootProof {
+                    slot: block_root.slot,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+// the proof must have exactly as many nodes as the depth of the gindex
+fn is_valid_proof(leaf: &H256, proof: &[H256], gindex: usize, root: &H256) -> bool {
+    let depth = (usize::BITS - 1 - gindex.leading_zeros()) as usize;
+    proof.len() == depth
+        && is_valid_merkle_branch(
+            &node_from_hash(leaf),
+            proof.iter().map(node_from_hash).collect::<Vec<_>>().iter(),
+            depth,
+            gindex,
+            &node_from_hash(root),
+        )
+}
+
+fn node_from_hash(h: &H256) -> Node {
+    Node::try_from(h.as_ref()).expect("is right size")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ssz_rs::compute_proof;
+    use zipline_spec::SpecTestSpec;
+
+    // a bellatrix state reduced to the fields the proofs go through. Its 25 fields are padded to 32 leaves
+    type State = Vector<Node, 32>;
+    const SLOT_FIELD: usize = 2;
+    const BLOCK_ROOTS_FIELD: usize = 5;
+    const SLOTS_PER_HISTORICAL_ROOT: usize = 8192;
+
+    fn to_h256(node: &Node) -> H256 {
+        node.as_ref().try_into().unwrap()
+    }
+
+    /// A candidate checkpoint for a state at `state_slot` with a distinct root in every entry of block_roots, and
+    /// ancestry proofs for the roots of the given slots
+    fn candidate_ancestry(state_slot: u64, slots: &[u64]) -> (Checkpoint, CandidateAncestry) {
+        let mut block_roots = Vector::<Node, SLOTS_PER_HISTORICAL_ROOT>::default();
+        for index in 0..SLOTS_PER_HISTORICAL_ROOT {
+            let mut root = [0_u8; 32];
+            root[..8].copy_from_slice(&(index as u64 + 1).to_le_bytes());
+            block_roots[index] = Node::try_from(root.as_slice()).unwrap();
+        }
+        let mut state = State::default();
+        state[SLOT_FIELD] = state_slot.clone().hash_tree_root().unwrap();
+        state[BLOCK_ROOTS_FIELD] = block_roots.hash_tree_root().unwrap();
+        let state_root = state.hash_tree_root().unwrap();
+        let mut state_tree = state.to_merkle_tree().unwrap();
+        state_tree.append(&mut block_roots.to_merkle_tree().unwrap());
+
+        let mut header = Vector::<Node, 8>::default();
+        header[3] = state_root;
+        let header_root = header.hash_tree_root().unwrap();
+        let header_tree = header.to_merkle_tree().unwrap();
+
+        let candidate_cp = Checkpoint {
+            epoch: SpecTestSpec::epoch(state_slot as usize) as u64,
+            root: to_h256(&header_root),
+        };
+        let proof = |gindex: u64, tree: &[([u8; 32], [u8; 64])], root: &Node| {
+            compute_proof(root, gindex as usize, tree).unwrap()
+        };
+        let block_roots = slots
+            .iter()
+            .map(|slot| {
+                let index = *slot as usize % SLOTS_PER_HISTORICAL_ROOT;
+                let gindex = SpecTestSpec::state_gindex(
+                    SpecTestSpec::block_roots_0_gindex(),
+                    candidate_cp.epoch,
+                ) + index as u64;
+                BlockRootProof {
+                    slot: *slot,
+                    root: to_h256(&block_roots[index]),
+                    proof: proof(gindex, &state_tree, &state_root).try_into().unwrap(),
+                }
+            })
+            .collect::<Vec<_>>();
+        let ancestry = CandidateAncestry {
+            state_root: to_h256(&state_root),
+            state_proof: proof(
+                STATE_ROOT_IN_HEADER_GINDEX as u64,
+                &header_tree,
+                &header_root,
+            )
+            .try_into()
+            .unwrap(),
+            slot: state_slot,
+            slot_proof: proof(
+                SpecTestSpec::state_gindex(SpecTestSpec::SLOT_GINDEX, candidate_cp.epoch),
+                &state_tree,
+                &state_root,
+            )
+            .try_into()
+            .unwrap(),
+            block_roots: block_roots.try_into().unwrap(),
+        };
+        (candidate_cp, ancestry)
+    }
+
+    #[test]
+    fn block_root_proofs_are_limited_to_the_block_roots_window() {
+        let state_slot = SLOTS_PER_HISTORICAL_ROOT as u64 + 10;
+        let oldest_slot = state_slot - SLOTS_PER_HISTORICAL_ROOT as u64;
+
+        // the oldest and newest roots still in block_roots
+        let (candidate_cp, ancestry) =
+            candidate_ancestry(state_slot, &[oldest_slot, state_slot - 1]);
+        assert_eq!(ancestry.validate::<SpecTestSpec>(&candidate_cp), Ok(()));
+
+        // the root of the slot before has been overwritten by the one of state_slot
+        let (candidate_cp, ancestry) = candidate_ancestry(state_slot, &[oldest_slot - 1]);
+        assert_eq!(
+            ancestry.validate::<SpecTestSpec>(&candidate_cp),
+            Err(AncestryRejection::SlotBeforeBlockRootsWindow {
+                slot: oldest_slot - 1,
+                oldest_slot
+            })
+        );
+        let (candidate_cp, ancestry) = candidate_ancestry(state_slot, &[state_slot]);
+        assert_eq!(
+            ancestry.validate::<SpecTestSpec>(&candidate_cp),
+            Err(AncestryRejection::SlotOutOfRange { slot: state_slot })
+        );
+    }
+}
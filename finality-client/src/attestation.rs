@@ -5,6 +5,7 @@ use alloc::vec::Vec;
 use crypto::bls::BLS_SIGNATURE_BYTES_LEN;
 use crypto::hash::H256;
 use ssz_rs::prelude::*;
+use zipline_spec::Spec;
 
 #[derive(Clone, Debug, Default, SimpleSerialize, PartialEq)]
 
@@ -30,6 +31,68 @@ impl AttestationData {
             target: self.target,
         }
     }
+
+    /// Checks the data could belong to an attestation in an epoch with `committees_per_slot` committees per slot.
+    /// This does not depend on who participates so it is checked before looking up the committee
+    pub fn validate<S: Spec>(
+        &self,
+        committees_per_slot: usize,
+    ) -> Result<(), AttestationRejection> {
+        let slot_epoch = S::epoch(self.slot as usize) as u64;
+        if self.target.epoch != slot_epoch {
+            return Err(AttestationRejection::TargetEpoch {
+                slot_epoch,
+                target_epoch: self.target.epoch,
+            });
+        }
+        if self.index >= committees_per_slot as u64 {
+            return Err(AttestationRejection::CommitteeIndex {
+                index: self.index,
+                committees_per_slot: committees_per_slot as u64,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The indices of the validators in `committee` with their aggregation bit set. As in `is_valid_indexed_attestation`
+/// there must be at least one
+pub fn get_attesting_indices_from_bits<const MAX_COMMITTEE_SIZE: usize>(
+    committee: &[usize],
+    aggregation_bits: &Bitlist<MAX_COMMITTEE_SIZE>,
+) -> Result<Vec<usize>, AttestationRejection> {
+    if aggregation_bits.len() != committee.len() {
+        return Err(AttestationRejection::AggregationBitsLength {
+            expected: committee.len(),
+            found: aggregation_bits.len(),
+        });
+    }
+    let indices: Vec<usize> = committee
+        .iter()
+        .zip(aggregation_bits.iter())
+        .filter(|(_, bit)| **bit)
+        .map(|(validator_index, _)| *validator_index)
+        .collect();
+    if indices.is_empty() {
+        return Err(AttestationRejection::NoParticipants);
+    }
+    Ok(indices)
+}
+
+/// Ways an attestation can be malformed. `verify` skips these attestations rather than failing
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttestationRejection {
+    /// The target is not the epoch of the attestation slot
+    TargetEpoch { slot_epoch: u64, target_epoch: u64 },
+    /// There is no committee with this index in the slot
+    CommitteeIndex {
+        index: u64,
+        committees_per_slot: u64,
+    },
+    /// The aggregation bits are not the length of the committee
+    AggregationBitsLength { expected: usize, found: usize },
+    /// No validator in the committee participates
+    NoParticipants,
 }
 #[derive(Default, Copy, Clone, SimpleSerialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Checkpoint {
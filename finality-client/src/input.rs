@@ -1,7 +1,10 @@
+use crate::active_set::ActiveSetHint;
+use crate::ancestry::CandidateAncestry;
 use crate::attestation::{Attestation, Checkpoint};
 use crate::state_patch::StatePatch;
+use crate::super_attestation::SuperAttestation;
 use alloc::{vec, vec::Vec};
-use crypto::hash::H256;
+use crypto::hash::{hash_fixed, H256};
 use ssz_rs::prelude::*;
 
 /// An SSZ container capturing all of the inputs required for one call to 'verify'
@@ -17,13 +20,27 @@ pub struct ZiplineInput<
     pub patches: List<StatePatch, MAX_PATCHES>,
     pub attestations: List<Attestation<MAX_COMMITTEE_SIZE>, MAX_ATTESTATIONS>,
     pub state_proof: List<H256, 3>, // SSZ proof that the state root is contained in the trusted_cp block
+    pub candidate_ancestry: CandidateAncestry, // block roots to finalize along with the candidate
+    pub super_attestation: SuperAttestation<MAX_COMMITTEE_SIZE, MAX_ATTESTATIONS>, // attestations sharing one aggregate signature
+    pub uncompressed_pubkeys_root: H256, // root of the uncompressed validator pubkeys list in the preimage oracle. Zero if not provided
+    pub active_set_hint: ActiveSetHint, // active validators and total balance in the trusted epoch. Empty if not provided
 }
 
 impl<const MAX_COMMITTEE_SIZE: usize, const MAX_ATTESTATIONS: usize, const MAX_PATCHES: usize>
     ZiplineInput<MAX_COMMITTEE_SIZE, MAX_ATTESTATIONS, MAX_PATCHES>
 {
     /// Deserialize from SSZ encoded bytes
-    pub fn from_ssz_bytes(bytes: &[u8]) -> Self {
-        <Self as ssz_rs::Deserialize>::deserialize(bytes).unwrap()
+    pub fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        <Self as ssz_rs::Deserialize>::deserialize(bytes)
+    }
+
+    /// The root of the uncompressed validator public keys if one was provided
+    pub fn uncompressed_pubkeys(&self) -> Option<H256> {
+        (self.uncompressed_pubkeys_root != [0; 32]).then_some(self.uncompressed_pubkeys_root)
+    }
+
+    /// The sha256 hash of the SSZ encoded input. This is the hash the input is retrieved by from the preimage oracle
+    pub fn hash(&self) -> Result<H256, SerializeError> {
+        Ok(hash_fixed(&ssz_rs::serialize(self)?))
     }
 }
@@ -3,14 +3,21 @@
 #![feature(iterator_try_reduce)]
 #![doc = include_str!("../README.md")]
 
+pub mod active_set;
+pub mod ancestry;
 pub mod attestation;
 pub mod input;
+pub mod output;
 pub mod signing;
 pub mod ssz_state_reader;
 pub mod state_patch;
 pub mod state_reader;
+pub mod super_attestation;
 pub mod verify;
 
 pub use verify::*;
 
 extern crate alloc;
+
+#[cfg(test)]
+mod test_state_reader;
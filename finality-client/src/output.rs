@@ -0,0 +1,87 @@
+use crate::attestation::Checkpoint;
+use crate::input::ZiplineInput;
+use crate::verify::VerificationOutcome;
+use alloc::{vec, vec::Vec};
+use crypto::hash::H256;
+use ssz_rs::prelude::*;
+
+/// Verdict codes committed to by a [`ZiplineOutput`]
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// verify ran to completion but the candidate checkpoint was not finalized
+    NotFinalized = 0,
+    /// verify ran to completion and the candidate checkpoint was finalized
+    Finalized = 1,
+    /// the input could not be decoded or was rejected by verify with an error
+    InvalidInput = 2,
+}
+
+impl From<&VerificationOutcome> for Verdict {
+    fn from(outcome: &VerificationOutcome) -> Self {
+        if outcome.is_finalized() {
+            Verdict::Finalized
+        } else {
+            Verdict::NotFinalized
+        }
+    }
+}
+
+/// An SSZ container capturing the result of one call to 'verify'.
+/// Its hash tree root is what the provable execution writes as its output hash
+/// so a verifier can distinguish between the possible verdicts for a given input
+#[derive(Clone, Debug, Default, SimpleSerialize, PartialEq)]
+pub struct ZiplineOutput {
+    pub input_hash: H256, // sha256 of the SSZ encoded input
+    pub candidate_cp: Checkpoint,
+    pub verdict: u8,
+}
+
+impl ZiplineOutput {
+    pub fn new(input_hash: H256, candidate_cp: Checkpoint, verdict: Verdict) -> Self {
+        Self {
+            input_hash,
+            candidate_cp,
+            verdict: verdict as u8,
+        }
+    }
+
+    /// The output for an input that was rejected. This does not commit to a candidate
+    /// as it may not be possible to decode one from the input
+    pub fn invalid_input(input_hash: H256) -> Self {
+        Self::new(input_hash, Checkpoint::default(), Verdict::InvalidInput)
+    }
+
+    /// Build the output expected from verifying the given input with the given verdict
+    pub fn for_input<
+        const MAX_COMMITTEE_SIZE: usize,
+        const MAX_ATTESTATIONS: usize,
+        const MAX_PATCHES: usize,
+    >(
+        input: &ZiplineInput<MAX_COMMITTEE_SIZE, MAX_ATTESTATIONS, MAX_PATCHES>,
+        verdict: Verdict,
+    ) -> Result<Self, SerializeError> {
+        Ok(Self::new(input.hash()?, input.candidate_cp, verdict))
+    }
+
+    /// The output hash that commits to this result
+    pub fn hash(&mut self) -> Result<H256, MerkleizationError> {
+        let root = self.hash_tree_root()?;
+        Ok(root.as_ref().try_into().expect("is right size"))
+    }
+}
+
+/// Compute the output hash expected from verifying the given input with the given verdict
+pub fn expected_output_hash<
+    const MAX_COMMITTEE_SIZE: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_PATCHES: usize,
+>(
+    input: &ZiplineInput<MAX_COMMITTEE_SIZE, MAX_ATTESTATIONS, MAX_PATCHES>,
+    verdict: Verdict,
+) -> H256 {
+    ZiplineOutput::for_input(input, verdict)
+        .expect("input can always be serialized")
+        .hash()
+        .expect("output can always be merkleized")
+}
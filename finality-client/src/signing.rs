@@ -1,6 +1,9 @@
-use crate::attestation::Attestation;
+use crate::attestation::{Attestation, AttestationData};
 use alloc::{vec, vec::Vec};
-use crypto::bls::{fast_aggregate_verify, BlsError, PublicKey, Signature};
+use crypto::bls::{
+    aggregate_verify, fast_aggregate_verify, find_invalid_signatures, BlsError, PublicKey,
+    Signature,
+};
 use ssz_rs::prelude::*;
 use zipline_spec::Spec;
 pub type Domain = [u8; 32];
@@ -47,7 +50,7 @@ pub fn verify_signed_attestation<S: Spec, const MAX_COMMITTEE_SIZE: usize>(
     a: &mut Attestation<MAX_COMMITTEE_SIZE>,
     public_keys: &[PublicKey],
 ) -> Result<(), SigningError> {
-    let domain = S::beacon_attester_signing_domain();
+    let domain = S::beacon_attester_signing_domain(a.data.target.epoch);
     let signing_root = compute_signing_root(&mut a.data, domain)?;
     fast_aggregate_verify(
         public_keys,
@@ -56,3 +59,30 @@ pub fn verify_signed_attestation<S: Spec, const MAX_COMMITTEE_SIZE: usize>(
     )
     .map_err(Into::into)
 }
+
+pub fn attestation_signing_root<S: Spec>(data: &mut AttestationData) -> Result<Root, SigningError> {
+    let domain = S::beacon_attester_signing_domain(data.target.epoch);
+    compute_signing_root(data, domain)
+}
+
+// Verify a single signature aggregated over many attestations. There must be one signing root and one
+// (aggregate) public key for each attestation that was signed
+pub fn verify_super_attestation_signature(
+    signing_roots: &[Root],
+    public_keys: &[PublicKey],
+    signature: &[u8],
+) -> Result<(), SigningError> {
+    let messages = signing_roots.iter().map(|r| r.as_ref()).collect::<Vec<_>>();
+    aggregate_verify(public_keys, &messages, &Signature::from_bytes(signature)?).map_err(Into::into)
+}
+
+// Verify the signatures of many attestations as a single batch. There must be one signing root, (aggregate) public key
+// and signature for each attestation. Returns the indices of the attestations whose signature is invalid
+pub fn find_invalid_attestation_signatures(
+    signing_roots: &[Root],
+    public_keys: &[PublicKey],
+    signatures: &[Signature],
+) -> Result<Vec<usize>, SigningError> {
+    let messages = signing_roots.iter().map(|r| r.as_ref()).collect::<Vec<_>>();
+    find_invalid_signatures(public_keys, &messages, signatures).map_err(Into::into)
+}
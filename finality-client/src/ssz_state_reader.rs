@@ -1,43 +1,101 @@
+use crate::active_set::{is_active_validator, ActiveSet, ActiveSetHint};
+use crate::attestation::Checkpoint;
 use crate::state_patch::StatePatch;
 use crate::state_reader::{PatchedStateReader, StateReadError, StateReader};
 use alloc::vec::Vec;
+use core::cell::RefCell;
 use core::marker::PhantomData;
 use crypto::bls::PublicKey;
 use crypto::hash::H256;
 use preimage_oracle::SszOracle;
-use typenum::Unsigned;
 use validator_shuffling::get_randao_index;
-use zipline_spec::Spec;
+use zipline_spec::{gindex_at_state_depth, Spec};
 
+// the fields of a validator needed to find the active validators. These are always loaded for every validator
 pub(crate) struct ValidatorInfo {
-    pub pubkey: PublicKey,
-    pub effective_balance: u64,
     pub activation_epoch: u64,
     pub exit_epoch: u64,
 }
+
+// the remaining fields of a validator that are used
+#[derive(Clone, Copy)]
+pub(crate) struct ValidatorDetails {
+    pub effective_balance: u64,
+    pub slashed: bool,
+    pub activation_eligibility_epoch: u64,
+}
+
 pub struct SszStateReader<TSsz: SszOracle, TSpec> {
     oracle: TSsz,
     root: H256,
     spec: PhantomData<TSpec>,
     validator_cache: Vec<ValidatorInfo>,
+    // in lazy mode the other fields are only loaded the first time a validator is used
+    details_cache: RefCell<Vec<Option<ValidatorDetails>>>,
+    // public keys are the most expensive part of a validator to load so they can be loaded only when first used
+    pubkey_cache: RefCell<Vec<Option<PublicKey>>>,
+    // root of an SSZ List[Vector[uint8, 96], VALIDATOR_REGISTRY_LIMIT] of the uncompressed validator public keys
+    uncompressed_pubkeys_root: Option<H256>,
+    // active validators checked against a hint. Used in place of scanning the validators when possible
+    active_set: Option<ActiveSet>,
+    // depth of the state tree for the fork of the state. The Spec gindex constants are translated to this depth
+    state_tree_depth: u32,
 }
 
 impl<TSsz: SszOracle, TSpec: Spec> SszStateReader<TSsz, TSpec> {
-    fn build_validator_cache(&mut self) -> Result<(), StateReadError> {
+    fn state_gindex(&self, gindex: u64) -> u64 {
+        gindex_at_state_depth(gindex, self.state_tree_depth)
+    }
+
+    fn checkpoint(&self, checkpoint_gindex: u64) -> Result<Checkpoint, StateReadError> {
+        // a checkpoint is the container (epoch, root)
+        let checkpoint_gindex = self.state_gindex(checkpoint_gindex);
+        Ok(Checkpoint {
+            epoch: self
+                .oracle
+                .map_as_uint64(self.root, checkpoint_gindex * 2, |x| x)?,
+            root: self
+                .oracle
+                .copy_chunk(self.root, checkpoint_gindex * 2 + 1)?,
+        })
+    }
+
+    // load the activation and exit epochs of every validator. Unless the reader is lazy the rest of each validator
+    // is loaded as well
+    fn build_validator_cache(&mut self, lazy: bool) -> Result<(), StateReadError> {
         log::debug!("Starting to build validator cache");
-        let start_index = TSpec::Validators0Gindex::to_u64();
+        let start_index = self.state_gindex(TSpec::VALIDATORS_0_GINDEX);
+
+        let activation_epoch_gindex = TSpec::ACTIVATION_EPOCH_GINDEX;
+        let exit_epoch_gindex = TSpec::EXIT_EPOCH_GINDEX;
 
-        let val_bal_gindex = TSpec::EffectiveBalanceGindex::to_u64();
-        let val_key_gindex = TSpec::PubkeyGindex::to_u64();
-        let activation_epoch_gindex = TSpec::ActivationEpochGindex::to_u64();
-        let exit_epoch_gindex = TSpec::ExitEpochGindex::to_u64();
+        let count = self.get_validator_count()?;
 
-        let count = self.get_validator_count().unwrap();
+        // if they are provided, uncompressed keys can be used to avoid decompressing every validator pubkey
+        let mut uncompressed_pubkeys = match self.uncompressed_pubkeys_root {
+            Some(pubkeys_root) => {
+                // gindex 3 is the length mixed in to the list root
+                if self.oracle.map_as_uint64(pubkeys_root, 3, |x| x)? != count as u64 {
+                    return Err(StateReadError::UncompressedPubkeysLength);
+                }
+                let depth = TSpec::VALIDATORS_TREE_DEPTH as u64;
+                Some(preimage_oracle::ssz_retrieval::iterate_nodes_at_depth(
+                    &self.oracle,
+                    pubkeys_root,
+                    depth,
+                    1 << depth,
+                    count as u64,
+                ))
+            }
+            None => None,
+        };
 
+        let mut details = Vec::with_capacity(count);
+        let mut pubkeys = Vec::with_capacity(count);
         let the_iter = preimage_oracle::ssz_retrieval::iterate_nodes_at_depth(
             &self.oracle,
             self.root,
-            46,
+            self.state_tree_depth as u64 + TSpec::VALIDATORS_TREE_DEPTH as u64,
             start_index,
             count as u64,
         )
@@ -46,17 +104,6 @@ impl<TSsz: SszOracle, TSpec: Spec> SszStateReader<TSsz, TSpec> {
             if i % 5000 == 0 {
                 log::debug!("Building Validator cache {}/{}", i, count);
             }
-            let mut pk: [u8; 48] = [0; 48];
-            self.oracle
-                .map_chunk(val_i_root, val_key_gindex, |k_root| {
-                    self.oracle.map(*k_root, |k| {
-                        pk.copy_from_slice(&k[0..48]);
-                    })
-                })??;
-
-            let balance = self
-                .oracle
-                .map_as_uint64(val_i_root, val_bal_gindex, |x| x)?;
             let activation_epoch =
                 self.oracle
                     .map_as_uint64(val_i_root, activation_epoch_gindex, |x| x)?;
@@ -64,25 +111,198 @@ impl<TSsz: SszOracle, TSpec: Spec> SszStateReader<TSsz, TSpec> {
                 .oracle
                 .map_as_uint64(val_i_root, exit_epoch_gindex, |x| x)?;
 
+            if lazy {
+                details.push(None);
+                pubkeys.push(None);
+            } else {
+                details.push(Some(self.read_details(val_i_root)?));
+                let uncompressed_root = uncompressed_pubkeys.as_mut().and_then(Iterator::next);
+                pubkeys.push(Some(self.read_pubkey(i, val_i_root, uncompressed_root)?));
+            }
+
             Ok(ValidatorInfo {
-                pubkey: PublicKey::from_bytes(&pk)?,
-                effective_balance: balance,
                 activation_epoch,
                 exit_epoch,
             })
         });
         self.validator_cache = the_iter.collect::<Result<Vec<_>, StateReadError>>()?;
+        self.details_cache = RefCell::new(details);
+        self.pubkey_cache = RefCell::new(pubkeys);
         Ok(())
     }
-    pub fn new(oracle: TSsz, root: H256) -> Result<Self, StateReadError> {
+
+    // read the fields of the validator with the given root that are not needed to find the active validators
+    fn read_details(&self, val_i_root: H256) -> Result<ValidatorDetails, StateReadError> {
+        Ok(ValidatorDetails {
+            effective_balance: self.oracle.map_as_uint64(
+                val_i_root,
+                TSpec::EFFECTIVE_BALANCE_GINDEX,
+                |x| x,
+            )?,
+            // a boolean is a single byte in its chunk
+            slashed: self
+                .oracle
+                .map_as_uint64(val_i_root, TSpec::SLASHED_GINDEX, |x| x != 0)?,
+            activation_eligibility_epoch: self.oracle.map_as_uint64(
+                val_i_root,
+                TSpec::ACTIVATION_ELIGIBILITY_EPOCH_GINDEX,
+                |x| x,
+            )?,
+        })
+    }
+
+    // read the public key of the validator with the given root, using the uncompressed key with the given root if there is one
+    fn read_pubkey(
+        &self,
+        validator_index: usize,
+        val_i_root: H256,
+        uncompressed_root: Option<H256>,
+    ) -> Result<PublicKey, StateReadError> {
+        let mut pk: [u8; 48] = [0; 48];
+        self.oracle
+            .map_chunk(val_i_root, TSpec::PUBKEY_GINDEX, |k_root| {
+                self.oracle.map(*k_root, |k| {
+                    pk.copy_from_slice(&k[0..48]);
+                })
+            })??;
+
+        match uncompressed_root {
+            Some(uncompressed_root) => {
+                let pubkey = PublicKey::from_uncompressed(
+                    &self.read_uncompressed_pubkey(uncompressed_root)?,
+                )?;
+                // compressing is cheap and checks both the x-coordinate and the sign of y
+                if pubkey.to_bytes() != pk {
+                    return Err(StateReadError::UncompressedPubkeyMismatch(validator_index));
+                }
+                Ok(pubkey)
+            }
+            None => Ok(PublicKey::from_bytes(&pk)?),
+        }
+    }
+
+    // read a Vector[uint8, 96] given its root. The three chunks are padded to a tree of depth 2
+    fn read_uncompressed_pubkey(&self, root: H256) -> Result<[u8; 96], StateReadError> {
+        let mut bytes = [0_u8; 96];
+        let (left, right) = self.oracle.map(root, |d| {
+            let mut left = [0_u8; 32];
+            let mut right = [0_u8; 32];
+            left.copy_from_slice(&d[0..32]);
+            right.copy_from_slice(&d[32..64]);
+            (left, right)
+        })?;
+        self.oracle
+            .map(left, |d| bytes[0..64].copy_from_slice(&d[0..64]))?;
+        self.oracle
+            .map(right, |d| bytes[64..96].copy_from_slice(&d[0..32]))?;
+        Ok(bytes)
+    }
+
+    fn validator_info(&self, validator_index: usize) -> Result<&ValidatorInfo, StateReadError> {
+        self.validator_cache
+            .get(validator_index)
+            .ok_or(StateReadError::ValidatorIndexOutOfRange(validator_index))
+    }
+
+    fn validator_root(&self, validator_index: usize) -> Result<H256, StateReadError> {
+        Ok(self.oracle.copy_chunk(
+            self.root,
+            self.state_gindex(TSpec::VALIDATORS_0_GINDEX) + validator_index as u64,
+        )?)
+    }
+
+    // the remaining fields of a validator, loading them from the oracle if this is the first time they are used
+    fn validator_details(
+        &self,
+        validator_index: usize,
+    ) -> Result<ValidatorDetails, StateReadError> {
+        if let Some(details) = self
+            .details_cache
+            .borrow()
+            .get(validator_index)
+            .ok_or(StateReadError::ValidatorIndexOutOfRange(validator_index))?
+        {
+            return Ok(*details);
+        }
+
+        let details = self.read_details(self.validator_root(validator_index)?)?;
+        self.details_cache.borrow_mut()[validator_index] = Some(details);
+        Ok(details)
+    }
+
+    // the public key of a validator, loading it from the oracle if this is the first time it is used
+    fn pubkey(&self, validator_index: usize) -> Result<PublicKey, StateReadError> {
+        if let Some(pubkey) = self
+            .pubkey_cache
+            .borrow()
+            .get(validator_index)
+            .ok_or(StateReadError::ValidatorIndexOutOfRange(validator_index))?
+        {
+            return Ok(pubkey.clone());
+        }
+
+        let val_i_root = self.validator_root(validator_index)?;
+        let uncompressed_root = match self.uncompressed_pubkeys_root {
+            Some(pubkeys_root) => Some(self.oracle.copy_chunk(
+                pubkeys_root,
+                (1 << TSpec::VALIDATORS_TREE_DEPTH) + validator_index as u64,
+            )?),
+            None => None,
+        };
+        let pubkey = self.read_pubkey(validator_index, val_i_root, uncompressed_root)?;
+        self.pubkey_cache.borrow_mut()[validator_index] = Some(pubkey.clone());
+        Ok(pubkey)
+    }
+
+    /// Create a reader for the state with the given root from the given epoch. The epoch gives the fork and so the
+    /// layout of the state. For the trusted state this is the epoch of the trusted checkpoint
+    pub fn new(oracle: TSsz, root: H256, epoch: u64) -> Result<Self, StateReadError> {
+        Self::build(oracle, root, epoch, None, false)
+    }
+
+    /// Create a reader that takes the validator public keys from the list of uncompressed keys with the given root
+    /// rather than decompressing the keys stored in the state
+    pub fn with_uncompressed_pubkeys(
+        oracle: TSsz,
+        root: H256,
+        epoch: u64,
+        uncompressed_pubkeys_root: H256,
+    ) -> Result<Self, StateReadError> {
+        Self::build(oracle, root, epoch, Some(uncompressed_pubkeys_root), false)
+    }
+
+    /// Create a reader that only loads the activation and exit epochs of every validator, as these are needed to find
+    /// the active validators. Public keys, effective balances and the other fields of a validator are retrieved from
+    /// the oracle the first time they are needed, so only validators in attesting committees have their keys loaded
+    pub fn lazy(
+        oracle: TSsz,
+        root: H256,
+        epoch: u64,
+        uncompressed_pubkeys_root: Option<H256>,
+    ) -> Result<Self, StateReadError> {
+        Self::build(oracle, root, epoch, uncompressed_pubkeys_root, true)
+    }
+
+    fn build(
+        oracle: TSsz,
+        root: H256,
+        epoch: u64,
+        uncompressed_pubkeys_root: Option<H256>,
+        lazy: bool,
+    ) -> Result<Self, StateReadError> {
         let mut reader = Self {
             oracle,
             root,
             spec: PhantomData,
             validator_cache: Vec::default(),
+            details_cache: RefCell::default(),
+            pubkey_cache: RefCell::default(),
+            uncompressed_pubkeys_root,
+            active_set: None,
+            state_tree_depth: TSpec::fork_at_epoch(epoch).state_tree_depth,
         };
 
-        reader.build_validator_cache()?;
+        reader.build_validator_cache(lazy)?;
 
         Ok(reader)
     }
@@ -95,7 +315,7 @@ impl<TSsz: SszOracle, TSpec: Spec> StateReader for SszStateReader<TSsz, TSpec> {
 
     fn get_validator_count(&self) -> Result<usize, StateReadError> {
         log::trace!("SszStateReader: get_validator_count");
-        let validator_count_gindex = TSpec::ValidatorsLengthGindex::to_u64();
+        let validator_count_gindex = self.state_gindex(TSpec::VALIDATORS_LENGTH_GINDEX);
         let validator_count =
             self.oracle
                 .map_as_uint64(self.root, validator_count_gindex, |x| x)?;
@@ -105,7 +325,7 @@ impl<TSsz: SszOracle, TSpec: Spec> StateReader for SszStateReader<TSsz, TSpec> {
     fn get_randao<S: Spec>(&self, epoch: u64) -> Result<[u8; 32], StateReadError> {
         log::trace!("SszStateReader get_randao({})", epoch);
         let randao_index = get_randao_index::<S>(epoch);
-        let randao_mixes_0_gindex = TSpec::RandaoMixes0Gindex::to_u64(); // TODO
+        let randao_mixes_0_gindex = self.state_gindex(TSpec::randao_mixes_0_gindex()); // TODO
         let randao_mixes_gindex = randao_mixes_0_gindex + randao_index as u64;
         let randao_mix = self.oracle.copy_chunk(self.root, randao_mixes_gindex)?;
 
@@ -114,41 +334,61 @@ impl<TSsz: SszOracle, TSpec: Spec> StateReader for SszStateReader<TSsz, TSpec> {
 
     fn get_active_validator_indices(&self, epoch: u64) -> Result<Vec<usize>, StateReadError> {
         log::trace!("SszStateReader get_active_validator_indices({})", epoch);
-        let start_index = TSpec::Validators0Gindex::to_u64();
-        let activation_epoch_gindex = TSpec::ActivationEpochGindex::to_u64();
-        let exit_epoch_gindex = TSpec::ExitEpochGindex::to_u64();
+        if let Some(indices) = self
+            .active_set
+            .as_ref()
+            .and_then(|active_set| active_set.active_validator_indices(epoch))
+        {
+            return Ok(indices);
+        }
+        Ok(self
+            .validator_cache
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.activation_epoch <= epoch && epoch < v.exit_epoch)
+            .map(|(i, _)| i)
+            .collect())
+    }
 
-        let count = self.get_validator_count()?;
-        Ok(preimage_oracle::ssz_retrieval::iterate_nodes_at_depth(
-            &self.oracle,
-            self.root,
-            46,
-            start_index,
-            count as u64,
-        )
-        .enumerate()
-        .filter_map(|val_i_root| {
-            if val_i_root.0 % 100000 == 0 {
-                log::trace!(
-                    "SszStateReader get_active_validator_indices validator {}",
-                    val_i_root.0
-                );
-            }
-            let activation = self
-                .oracle
-                .map_as_uint64(val_i_root.1, activation_epoch_gindex, |x| x)
-                .unwrap();
-            let exit = self
-                .oracle
-                .map_as_uint64(val_i_root.1, exit_epoch_gindex, |x| x)
-                .unwrap();
-            if (activation <= epoch) && (epoch < exit) {
-                Some(val_i_root.0)
-            } else {
-                None
-            }
-        })
-        .collect())
+    // overridden so the total balance can be found without loading any public keys
+    fn get_total_active_balance(&self, epoch: u64) -> Result<u64, StateReadError> {
+        log::trace!("SszStateReader get_total_active_balance({})", epoch);
+        if let Some(balance) = self
+            .active_set
+            .as_ref()
+            .and_then(|active_set| active_set.total_active_balance(epoch))
+        {
+            return Ok(balance);
+        }
+        let mut total = 0;
+        for validator_index in self.get_active_validator_indices(epoch)? {
+            total += self.validator_details(validator_index)?.effective_balance;
+        }
+        Ok(total)
+    }
+
+    // the hint is checked in a single pass over the cached validators. Only active validators count towards the
+    // total so only their balances are needed
+    fn apply_active_set_hint(
+        &mut self,
+        epoch: u64,
+        hint: &ActiveSetHint,
+    ) -> Result<(), StateReadError> {
+        let validators = self
+            .validator_cache
+            .iter()
+            .enumerate()
+            .map(|(validator_index, v)| {
+                let balance = if is_active_validator(v.activation_epoch, v.exit_epoch, epoch) {
+                    self.validator_details(validator_index)?.effective_balance
+                } else {
+                    0
+                };
+                Ok((v.activation_epoch, v.exit_epoch, balance))
+            })
+            .collect::<Result<Vec<_>, StateReadError>>()?;
+        self.active_set = Some(ActiveSet::verify(epoch, hint, validators.into_iter())?);
+        Ok(())
     }
 
     fn aggregate_validator_keys_and_balance(
@@ -162,13 +402,8 @@ impl<TSsz: SszOracle, TSpec: Spec> StateReader for SszStateReader<TSsz, TSpec> {
         let mut pk_acc: Vec<PublicKey> = Vec::with_capacity(indices.len());
         let mut bal_acc = 0;
         for idx in indices.iter() {
-            let ValidatorInfo {
-                pubkey: pk,
-                effective_balance: bal,
-                ..
-            } = &self.validator_cache[*idx];
-            pk_acc.push(pk.clone());
-            bal_acc += bal;
+            bal_acc += self.validator_details(*idx)?.effective_balance;
+            pk_acc.push(self.pubkey(*idx)?);
         }
         Ok((pk_acc, bal_acc))
     }
@@ -177,10 +412,67 @@ impl<TSsz: SszOracle, TSpec: Spec> StateReader for SszStateReader<TSsz, TSpec> {
         &self,
         validator_index: usize,
     ) -> Result<(u64, u64), StateReadError> {
-        Ok((
-            self.validator_cache[validator_index].activation_epoch,
-            self.validator_cache[validator_index].exit_epoch,
-        ))
+        let info = self.validator_info(validator_index)?;
+        Ok((info.activation_epoch, info.exit_epoch))
+    }
+
+    fn get_validator_activation_eligibility_epoch(
+        &self,
+        validator_index: usize,
+    ) -> Result<u64, StateReadError> {
+        Ok(self
+            .validator_details(validator_index)?
+            .activation_eligibility_epoch)
+    }
+
+    fn get_validator_effective_balance(
+        &self,
+        validator_index: usize,
+    ) -> Result<u64, StateReadError> {
+        Ok(self.validator_details(validator_index)?.effective_balance)
+    }
+
+    fn is_validator_slashed(&self, validator_index: usize) -> Result<bool, StateReadError> {
+        Ok(self.validator_details(validator_index)?.slashed)
+    }
+
+    fn get_finalized_epoch(&self) -> Result<u64, StateReadError> {
+        // epoch is the first field of the finalized checkpoint
+        let finalized_epoch_gindex = self.state_gindex(TSpec::FINALIZED_CHECKPOINT_GINDEX) * 2;
+        Ok(self
+            .oracle
+            .map_as_uint64(self.root, finalized_epoch_gindex, |x| x)?)
+    }
+
+    fn get_slot(&self) -> Result<u64, StateReadError> {
+        let slot_gindex = self.state_gindex(TSpec::SLOT_GINDEX);
+        Ok(self.oracle.map_as_uint64(self.root, slot_gindex, |x| x)?)
+    }
+
+    fn get_justification_bits(&self) -> Result<u8, StateReadError> {
+        // the 4 bits are packed into the first byte of the chunk
+        let bits_gindex = self.state_gindex(TSpec::JUSTIFICATION_BITS_GINDEX);
+        Ok(self
+            .oracle
+            .map_as_uint64(self.root, bits_gindex, |x| x as u8)?)
+    }
+
+    fn get_previous_justified_checkpoint(&self) -> Result<Checkpoint, StateReadError> {
+        self.checkpoint(TSpec::PREVIOUS_JUSTIFIED_CHECKPOINT_GINDEX)
+    }
+
+    fn get_current_justified_checkpoint(&self) -> Result<Checkpoint, StateReadError> {
+        self.checkpoint(TSpec::CURRENT_JUSTIFIED_CHECKPOINT_GINDEX)
+    }
+
+    fn get_block_root<S: Spec>(&self, slot: u64) -> Result<H256, StateReadError> {
+        let state_slot = self.get_slot()?;
+        if slot >= state_slot || state_slot - slot > S::slots_per_historical_root() as u64 {
+            return Err(StateReadError::BlockRootUnavailable(slot));
+        }
+        let block_roots_gindex = self.state_gindex(TSpec::block_roots_0_gindex())
+            + slot % S::slots_per_historical_root() as u64;
+        Ok(self.oracle.copy_chunk(self.root, block_roots_gindex)?)
     }
 }
 
@@ -1,4 +1,7 @@
+use crate::active_set::FAR_FUTURE_EPOCH;
+use crate::state_reader::{PatchedStateReader, StateReadError, StateReader};
 use alloc::{vec, vec::Vec};
+use crypto::bls::{PublicKey, BLS_PUBLIC_KEY_BYTES_LEN};
 use crypto::hash::H256;
 use log::trace;
 /// A state patch is a small amount of data that can be applied to an existing BeaconState so that it can be correctly used to
@@ -8,12 +11,16 @@ use log::trace;
 /// reduce the security.
 /// This does allow a potential attacker to manipulate the RANDO which we should be aware of. This means an attacker would be able to insert malicious validators
 /// and get them into the same committee. Even with this ability there is still only a minor impact to the economic security.
-/// TODO: Calculate exactly how much the security decays per epoch
+/// The decay for a given sequence of patches is bounded by `patch_influence`.
 use ssz_rs::prelude::*;
 use zipline_spec::Spec;
 // these are just temp for now, should read from a zipline spec
 const MAX_ACTIVATIONS: usize = 256;
 const MAX_EXITS: usize = 256;
+pub const MAX_BALANCE_UPDATES: usize = 1024;
+pub const MAX_SLASHINGS: usize = 1024;
+// MAX_DEPOSITS * SLOTS_PER_EPOCH, the most validators that can be created in an epoch
+pub const MAX_NEW_VALIDATORS: usize = 512;
 
 #[derive(Clone, Debug, Default, SimpleSerialize, PartialEq)]
 pub struct StatePatch {
@@ -22,27 +29,758 @@ pub struct StatePatch {
     pub exits: List<u32, MAX_EXITS>,
     pub n_deposits_processed: u32,
     pub randao_next: H256, // randao value needed to compute the shuffling in the NEXT epoch
+    pub effective_balance_updates: List<EffectiveBalanceUpdate, MAX_BALANCE_UPDATES>,
+    pub slashings: List<u32, MAX_SLASHINGS>, // validators slashed in this epoch
+    pub new_validators: List<ValidatorRecord, MAX_NEW_VALIDATORS>, // records of the validators created by the deposits processed. Empty if not provided
+}
+
+/// A validator as it appears in the registry, matching the phase0 `Validator` container
+#[derive(Clone, Debug, Default, SimpleSerialize, PartialEq)]
+pub struct ValidatorRecord {
+    pub pubkey: Vector<u8, BLS_PUBLIC_KEY_BYTES_LEN>,
+    pub withdrawal_credentials: H256,
+    pub effective_balance: u64,
+    pub slashed: bool,
+    pub activation_eligibility_epoch: u64,
+    pub activation_epoch: u64,
+    pub exit_epoch: u64,
+    pub withdrawable_epoch: u64,
+}
+
+/// The effective balance of a validator as of the patch epoch
+#[derive(Clone, Debug, Default, SimpleSerialize, PartialEq)]
+pub struct EffectiveBalanceUpdate {
+    pub validator_index: u32,
+    pub effective_balance: u64,
+}
+
+/// Reasons a state patch can be rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchRejection {
+    ActivationsExceedChurnLimit {
+        activations: u32,
+        churn_limit: u32,
+    },
+    ExitsExceedChurnLimit {
+        exits: u32,
+        churn_limit: u32,
+    },
+    TooManyDeposits {
+        n_deposits: u32,
+        max_deposits: u32,
+    },
+    ActivationEpochMismatch {
+        validator_index: u32,
+        activation_epoch: u64,
+    },
+    ActivationNotScheduled {
+        validator_index: u32,
+    },
+    NotEligibleForActivation {
+        validator_index: u32,
+        eligibility_epoch: u64,
+    },
+    ActivationQueueSkipped {
+        validator_index: u32,
+        skipped: u32,
+    },
+    ExitEpochMismatch {
+        validator_index: u32,
+        exit_epoch: u64,
+    },
+    ExitNotScheduled {
+        validator_index: u32,
+    },
+    ExitOfInactiveValidator {
+        validator_index: u32,
+    },
+    UnknownValidator {
+        validator_index: u32,
+    },
+    DuplicateBalanceUpdate {
+        validator_index: u32,
+    },
+    InvalidEffectiveBalance {
+        validator_index: u32,
+        effective_balance: u64,
+    },
+    EffectiveBalanceChangeTooLarge {
+        validator_index: u32,
+        from: u64,
+        to: u64,
+    },
+    AlreadySlashed {
+        validator_index: u32,
+    },
+    NewValidatorCount {
+        n_deposits: u32,
+        n_records: u32,
+    },
+    InvalidNewValidator {
+        deposit_index: u32,
+    },
 }
 
 impl StatePatch {
-    pub fn is_valid<S: Spec>(&self, n_active_validators: u32) -> bool {
+    pub fn validate<S: Spec>(&self, n_active_validators: u32) -> Result<(), PatchRejection> {
         let churn_limit = get_validator_churn_limit::<S>(n_active_validators);
-        if (self.activations.len() as u32) > churn_limit || (self.exits.len() as u32) > churn_limit
-        {
-            trace!("patch activations or exits exceeds churn limit");
-            return false;
+        if (self.activations.len() as u32) > churn_limit {
+            trace!("patch activations exceeds churn limit");
+            return Err(PatchRejection::ActivationsExceedChurnLimit {
+                activations: self.activations.len() as u32,
+                churn_limit,
+            });
+        }
+        if (self.exits.len() as u32) > churn_limit {
+            trace!("patch exits exceeds churn limit");
+            return Err(PatchRejection::ExitsExceedChurnLimit {
+                exits: self.exits.len() as u32,
+                churn_limit,
+            });
         }
 
-        if self.n_deposits_processed > S::max_deposits() * (S::slots_per_epoch() as u32) {
+        let max_deposits = S::max_deposits() * (S::slots_per_epoch() as u32);
+        if self.n_deposits_processed > max_deposits {
             trace!("patch n_deposits_processed exceeds max");
-            return false;
+            return Err(PatchRejection::TooManyDeposits {
+                n_deposits: self.n_deposits_processed,
+                max_deposits,
+            });
+        }
+
+        // if provided there must be a record for every deposit
+        if !self.new_validators.is_empty()
+            && self.new_validators.len() != self.n_deposits_processed as usize
+        {
+            trace!("patch new validators do not match n_deposits_processed");
+            return Err(PatchRejection::NewValidatorCount {
+                n_deposits: self.n_deposits_processed,
+                n_records: self.new_validators.len() as u32,
+            });
+        }
+        // a validator created by a deposit cannot have been activated, exited or slashed yet and its key must be valid
+        // for the deposit to have been processed
+        let increment = S::effective_balance_increment();
+        for (deposit_index, record) in self.new_validators.iter().enumerate() {
+            if record.activation_epoch != FAR_FUTURE_EPOCH
+                || record.exit_epoch != FAR_FUTURE_EPOCH
+                || record.slashed
+                || record.effective_balance % increment != 0
+                || record.effective_balance > S::max_effective_balance()
+                || PublicKey::from_bytes(&record.pubkey).is_err()
+            {
+                trace!("patch new validator {} is invalid", deposit_index);
+                return Err(PatchRejection::InvalidNewValidator {
+                    deposit_index: deposit_index as u32,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn is_valid<S: Spec>(&self, n_active_validators: u32) -> bool {
+        self.validate::<S>(n_active_validators).is_ok()
+    }
+
+    /// Check the effective balance updates could have been produced by the effective balance update rules.
+    /// Hysteresis means balances only change in whole increments, and rewards and penalties move a validator by at
+    /// most one increment in an epoch. Larger increases need a top-up deposit so are counted against the deposit
+    /// limit along with new validators. Larger decreases are rejected unless the validator is slashed, in the state
+    /// or by this patch, as the correlation penalty applied to slashed validators can take their whole balance.
+    /// The state reader must be the trusted state with all previous patches applied
+    pub fn validate_balance_updates<S: Spec, PSR: PatchedStateReader>(
+        &self,
+        state_reader: &PSR,
+    ) -> Result<Result<(), PatchRejection>, StateReadError> {
+        if self.effective_balance_updates.is_empty() {
+            return Ok(Ok(()));
+        }
+        let base_count = state_reader.reader().get_validator_count()?;
+        let increment = S::effective_balance_increment();
+        let mut top_ups = 0_u32;
+        for (n, update) in self.effective_balance_updates.iter().enumerate() {
+            let EffectiveBalanceUpdate {
+                validator_index,
+                effective_balance,
+            } = *update;
+            if validator_index as usize >= base_count {
+                trace!("balance update for unknown validator {}", validator_index);
+                return Ok(Err(PatchRejection::UnknownValidator { validator_index }));
+            }
+            if self.effective_balance_updates[..n]
+                .iter()
+                .any(|u| u.validator_index == validator_index)
+            {
+                trace!("duplicate balance update for validator {}", validator_index);
+                return Ok(Err(PatchRejection::DuplicateBalanceUpdate {
+                    validator_index,
+                }));
+            }
+            if effective_balance % increment != 0 || effective_balance > S::max_effective_balance()
+            {
+                trace!(
+                    "invalid effective balance for validator {}",
+                    validator_index
+                );
+                return Ok(Err(PatchRejection::InvalidEffectiveBalance {
+                    validator_index,
+                    effective_balance,
+                }));
+            }
+            let current = state_reader.get_validator_effective_balance(validator_index as usize)?;
+            if effective_balance > current.saturating_add(increment) {
+                top_ups += 1;
+            } else if effective_balance.saturating_add(increment) < current
+                && !self.slashings.contains(&validator_index)
+                && !state_reader.is_validator_slashed(validator_index as usize)?
+            {
+                trace!(
+                    "balance decrease too large for validator {}",
+                    validator_index
+                );
+                return Ok(Err(PatchRejection::EffectiveBalanceChangeTooLarge {
+                    validator_index,
+                    from: current,
+                    to: effective_balance,
+                }));
+            }
+        }
+        let max_deposits = S::max_deposits() * (S::slots_per_epoch() as u32);
+        let n_deposits = self.n_deposits_processed.saturating_add(top_ups);
+        if n_deposits > max_deposits {
+            trace!("patch deposits and top-ups exceed max");
+            return Ok(Err(PatchRejection::TooManyDeposits {
+                n_deposits,
+                max_deposits,
+            }));
+        }
+        Ok(Ok(()))
+    }
+
+    /// Check the slashed validators exist and were not already slashed, including earlier in this patch.
+    /// Slashing evidence is not part of the patch so it cannot be checked that a slashing actually happened.
+    /// The state reader must be the trusted state with all previous patches applied
+    pub fn validate_slashings<PSR: PatchedStateReader>(
+        &self,
+        state_reader: &PSR,
+    ) -> Result<Result<(), PatchRejection>, StateReadError> {
+        let base_count = state_reader.reader().get_validator_count()?;
+        for (n, validator_index) in self.slashings.iter().copied().enumerate() {
+            if validator_index as usize >= base_count {
+                trace!("slashing of unknown validator {}", validator_index);
+                return Ok(Err(PatchRejection::UnknownValidator { validator_index }));
+            }
+            if self.slashings[..n].contains(&validator_index)
+                || state_reader.is_validator_slashed(validator_index as usize)?
+            {
+                trace!("validator {} is already slashed", validator_index);
+                return Ok(Err(PatchRejection::AlreadySlashed { validator_index }));
+            }
+        }
+        Ok(Ok(()))
+    }
+
+    /// Check the activations and exits follow the phase0/bellatrix registry update rules as far as is possible
+    /// from the validator fields of the trusted state. The state reader must be the trusted state with all previous
+    /// patches applied. Validators created by deposits after the trusted state are checked using their records in
+    /// `new_validators` so these must be provided
+    pub fn validate_registry_updates<S: Spec, PSR: PatchedStateReader>(
+        &self,
+        state_reader: &PSR,
+        trusted_epoch: u64,
+    ) -> Result<Result<(), PatchRejection>, StateReadError> {
+        let count = state_reader.get_validator_count()?;
+        let max_seed_lookahead = S::max_seed_lookahead() as u64;
+        // registry updates processed in the trusted epoch or later cannot take effect before this epoch.
+        // Anything taking effect earlier must already be scheduled in the trusted state
+        let first_unscheduled_epoch = trusted_epoch.saturating_add(max_seed_lookahead);
+        // an activation processed in epoch E takes effect at E + max_seed_lookahead + 1 and E can be finalized
+        // at the earliest in epoch E + 1 so this is the latest epoch a validator can become eligible by
+        let latest_eligibility_epoch = self.epoch.saturating_sub(max_seed_lookahead + 2);
+
+        let mut queued = Vec::new();
+        for validator_index in self.activations.iter().copied() {
+            let i = validator_index as usize;
+            if i >= count {
+                trace!("activation of unknown validator {}", i);
+                return Ok(Err(PatchRejection::UnknownValidator { validator_index }));
+            }
+            let (activation_epoch, _) = state_reader.get_validator_activation_and_exit_epochs(i)?;
+            if activation_epoch != FAR_FUTURE_EPOCH {
+                if activation_epoch != self.epoch {
+                    trace!("patch activates validator {} at the wrong epoch", i);
+                    return Ok(Err(PatchRejection::ActivationEpochMismatch {
+                        validator_index,
+                        activation_epoch,
+                    }));
+                }
+                continue;
+            }
+            if self.epoch < first_unscheduled_epoch {
+                trace!("patch activates unscheduled validator {}", i);
+                return Ok(Err(PatchRejection::ActivationNotScheduled {
+                    validator_index,
+                }));
+            }
+            let eligibility_epoch = state_reader.get_validator_activation_eligibility_epoch(i)?;
+            if eligibility_epoch > latest_eligibility_epoch {
+                trace!("patch activates ineligible validator {}", i);
+                return Ok(Err(PatchRejection::NotEligibleForActivation {
+                    validator_index,
+                    eligibility_epoch,
+                }));
+            }
+            queued.push((eligibility_epoch, validator_index));
+        }
+
+        // the activation queue is ordered by (eligibility epoch, index) so every validator ahead of one activated from
+        // the queue must already be activated. Only validators known to be eligible can be checked. Validators created
+        // by deposits became eligible after the trusted state was finalized so are never among them
+        if let Some(&(last_eligibility, last_index)) = queued.iter().max() {
+            let finalized_epoch = state_reader.get_finalized_epoch()?;
+            for i in 0..state_reader.reader().get_validator_count()? {
+                let eligibility_epoch =
+                    state_reader.get_validator_activation_eligibility_epoch(i)?;
+                if eligibility_epoch > finalized_epoch
+                    || (eligibility_epoch, i as u64) >= (last_eligibility, last_index as u64)
+                    || self.activations.contains(&(i as u32))
+                {
+                    continue;
+                }
+                let (activation_epoch, _) =
+                    state_reader.get_validator_activation_and_exit_epochs(i)?;
+                if activation_epoch == FAR_FUTURE_EPOCH {
+                    trace!("patch activates validator {} ahead of {}", last_index, i);
+                    return Ok(Err(PatchRejection::ActivationQueueSkipped {
+                        validator_index: last_index,
+                        skipped: i as u32,
+                    }));
+                }
+            }
         }
 
-        true
+        for validator_index in self.exits.iter().copied() {
+            let i = validator_index as usize;
+            if i >= count {
+                trace!("exit of unknown validator {}", i);
+                return Ok(Err(PatchRejection::UnknownValidator { validator_index }));
+            }
+            let (activation_epoch, exit_epoch) =
+                state_reader.get_validator_activation_and_exit_epochs(i)?;
+            if exit_epoch != FAR_FUTURE_EPOCH && exit_epoch != self.epoch {
+                trace!("patch exits validator {} at the wrong epoch", i);
+                return Ok(Err(PatchRejection::ExitEpochMismatch {
+                    validator_index,
+                    exit_epoch,
+                }));
+            }
+            if exit_epoch == FAR_FUTURE_EPOCH && self.epoch < first_unscheduled_epoch {
+                trace!("patch exits unscheduled validator {}", i);
+                return Ok(Err(PatchRejection::ExitNotScheduled { validator_index }));
+            }
+            // the exit must have been initiated while the validator was active
+            if activation_epoch.saturating_add(max_seed_lookahead + 1) > self.epoch {
+                trace!("patch exits inactive validator {}", i);
+                return Ok(Err(PatchRejection::ExitOfInactiveValidator {
+                    validator_index,
+                }));
+            }
+        }
+
+        Ok(Ok(()))
     }
 }
 
+/// An upper bound on the active balance a sequence of patches could have inserted or removed from the base state.
+/// An adversarial patcher can activate, exit or slash any validators they choose (within the churn limit) so every
+/// patched validator is assumed to have been changed for the worst
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PatchInfluence {
+    pub inserted_balance: u64, // balance of all validators activated and balance increases by the patches
+    pub removed_balance: u64, // balance of all validators exited or slashed and balance decreases by the patches
+    pub total_active_balance: u64, // total active balance of the base state
+}
+
+impl PatchInfluence {
+    pub const BASIS_POINTS: u64 = 10_000;
+
+    /// The worst-case fraction of the base total active balance that was inserted or removed, in basis points.
+    /// Rounded up so this is never an underestimate
+    pub fn basis_points(&self) -> u64 {
+        let changed = self.inserted_balance as u128 + self.removed_balance as u128;
+        let total = core::cmp::max(self.total_active_balance, 1) as u128;
+        let bps = (changed * Self::BASIS_POINTS as u128 + total - 1) / total;
+        u64::try_from(bps).unwrap_or(u64::MAX)
+    }
+}
+
+/// Compute the influence of the given patches on the validator set of the state at the given epoch.
+/// Validators that are not in the base state (e.g. created by deposits) are assumed to have the max effective balance
+pub fn patch_influence<S: Spec, SR: StateReader>(
+    state_reader: &SR,
+    epoch: u64,
+    patches: &[StatePatch],
+) -> Result<PatchInfluence, StateReadError> {
+    let validator_count = state_reader.get_validator_count()?;
+    let balance_of = |validator_index: u32| -> Result<u64, StateReadError> {
+        let validator_index = validator_index as usize;
+        if validator_index < validator_count {
+            state_reader.get_validator_effective_balance(validator_index)
+        } else {
+            Ok(S::max_effective_balance())
+        }
+    };
+    let mut influence = PatchInfluence {
+        total_active_balance: state_reader.get_total_active_balance(epoch)?,
+        ..Default::default()
+    };
+    for patch in patches {
+        for validator_index in patch.activations.iter() {
+            influence.inserted_balance = influence
+                .inserted_balance
+                .saturating_add(balance_of(*validator_index)?);
+        }
+        for validator_index in patch.exits.iter() {
+            influence.removed_balance = influence
+                .removed_balance
+                .saturating_add(balance_of(*validator_index)?);
+        }
+        // slashed validators remain active but no longer add attesting weight
+        for validator_index in patch.slashings.iter() {
+            influence.removed_balance = influence
+                .removed_balance
+                .saturating_add(balance_of(*validator_index)?);
+        }
+        // balance updates are compared to the base state which bounds the change from any sequence of updates
+        for update in patch.effective_balance_updates.iter() {
+            let base_balance = balance_of(update.validator_index)?;
+            if update.effective_balance > base_balance {
+                influence.inserted_balance = influence
+                    .inserted_balance
+                    .saturating_add(update.effective_balance - base_balance);
+            } else {
+                influence.removed_balance = influence
+                    .removed_balance
+                    .saturating_add(base_balance - update.effective_balance);
+            }
+        }
+    }
+    Ok(influence)
+}
+
 // https://eth2book.info/bellatrix/part3/helper/accessors/#get_validator_churn_limit
 fn get_validator_churn_limit<S: Spec>(n_active_validators: u32) -> u32 {
-    core::cmp::max(S::min_per_epoch_churn_limit(), n_active_validators) / S::churn_limit_quotient()
+    core::cmp::max(
+        S::min_per_epoch_churn_limit(),
+        n_active_validators / S::churn_limit_quotient(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_state_reader::{validator, PatchedTestStateReader, TestStateReader};
+    use zipline_spec::SpecTestSpec;
+
+    #[test]
+    fn deposited_validators_follow_the_registry_update_rules() {
+        let trusted_epoch = 10;
+        let max_seed_lookahead = SpecTestSpec::max_seed_lookahead() as u64;
+        let deposit_epoch = trusted_epoch + max_seed_lookahead;
+        let state = TestStateReader::with_validators::<SpecTestSpec>(4);
+        let mut patched = PatchedTestStateReader::new(state);
+        for epoch in trusted_epoch + 1..deposit_epoch {
+            patched = patched.with_patch(StatePatch {
+                epoch,
+                ..Default::default()
+            });
+        }
+        // the deposit is processed in the last patch so the validator becomes eligible in the next epoch
+        let mut deposit = StatePatch {
+            epoch: deposit_epoch,
+            n_deposits_processed: 1,
+            ..Default::default()
+        };
+        let mut record = validator::<SpecTestSpec>(FAR_FUTURE_EPOCH);
+        record.activation_eligibility_epoch = deposit_epoch + 1;
+        deposit.new_validators.push(record);
+        let patched = patched.with_patch(deposit);
+
+        let activation = StatePatch {
+            epoch: deposit_epoch + 1,
+            activations: vec![4].try_into().unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(
+            activation
+                .validate_registry_updates::<SpecTestSpec, _>(&patched, trusted_epoch)
+                .unwrap(),
+            Err(PatchRejection::NotEligibleForActivation {
+                validator_index: 4,
+                eligibility_epoch: deposit_epoch + 1,
+            })
+        );
+
+        // validators beyond the deposits do not exist
+        let activation = StatePatch {
+            epoch: deposit_epoch + 1,
+            exits: vec![5].try_into().unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(
+            activation
+                .validate_registry_updates::<SpecTestSpec, _>(&patched, trusted_epoch)
+                .unwrap(),
+            Err(PatchRejection::UnknownValidator { validator_index: 5 })
+        );
+    }
+
+    const TRUSTED_EPOCH: u64 = 10;
+
+    fn balance() -> u64 {
+        SpecTestSpec::max_effective_balance()
+    }
+
+    fn increment() -> u64 {
+        SpecTestSpec::effective_balance_increment()
+    }
+
+    fn balance_update(validator_index: u32, effective_balance: u64) -> EffectiveBalanceUpdate {
+        EffectiveBalanceUpdate {
+            validator_index,
+            effective_balance,
+        }
+    }
+
+    fn unpatched(n_validators: usize) -> PatchedTestStateReader {
+        PatchedTestStateReader::new(TestStateReader::with_validators::<SpecTestSpec>(
+            n_validators,
+        ))
+    }
+
+    #[test]
+    fn patch_influence_counts_every_patched_validator() {
+        let mut state = TestStateReader::with_validators::<SpecTestSpec>(4);
+        state.validators[0].effective_balance = balance() - increment();
+        let patches = [
+            StatePatch {
+                epoch: TRUSTED_EPOCH + 1,
+                activations: vec![0].try_into().unwrap(),
+                exits: vec![1, 4].try_into().unwrap(),
+                ..Default::default()
+            },
+            StatePatch {
+                epoch: TRUSTED_EPOCH + 2,
+                slashings: vec![2].try_into().unwrap(),
+                effective_balance_updates: vec![balance_update(3, balance() - increment())]
+                    .try_into()
+                    .unwrap(),
+                ..Default::default()
+            },
+        ];
+        let influence =
+            patch_influence::<SpecTestSpec, _>(&state, TRUSTED_EPOCH, &patches).unwrap();
+        assert_eq!(
+            influence,
+            PatchInfluence {
+                inserted_balance: balance() - increment(),
+                // validator 4 is not in the base state so is assumed to have the max effective balance
+                removed_balance: 3 * balance() + increment(),
+                total_active_balance: 4 * balance() - increment(),
+            }
+        );
+        assert!(influence.basis_points() > 0);
+    }
+
+    #[test]
+    fn churn_limit_is_at_least_the_minimum() {
+        let min = SpecTestSpec::min_per_epoch_churn_limit();
+        let quotient = SpecTestSpec::churn_limit_quotient();
+        let patch_with = |activations: u32| StatePatch {
+            activations: (0..activations).collect::<Vec<_>>().try_into().unwrap(),
+            ..Default::default()
+        };
+        // small validator sets can always churn the minimum
+        assert!(patch_with(min).is_valid::<SpecTestSpec>(64));
+        assert_eq!(
+            patch_with(min + 1).validate::<SpecTestSpec>(64),
+            Err(PatchRejection::ActivationsExceedChurnLimit {
+                activations: min + 1,
+                churn_limit: min
+            })
+        );
+        // large ones churn a fraction of the active validators
+        let n_active = (min + 2) * quotient;
+        assert!(patch_with(min + 2).is_valid::<SpecTestSpec>(n_active));
+        assert!(!patch_with(min + 3).is_valid::<SpecTestSpec>(n_active));
+    }
+
+    #[test]
+    fn exits_must_be_scheduled() {
+        let mut state = TestStateReader::with_validators::<SpecTestSpec>(4);
+        state.validators[0].exit_epoch = TRUSTED_EPOCH + 1;
+        let patched = PatchedTestStateReader::new(state);
+        let exit = |validator_index: u32| StatePatch {
+            epoch: TRUSTED_EPOCH + 1,
+            exits: vec![validator_index].try_into().unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(
+            exit(0)
+                .validate_registry_updates::<SpecTestSpec, _>(&patched, TRUSTED_EPOCH)
+                .unwrap(),
+            Ok(())
+        );
+        assert_eq!(
+            exit(1)
+                .validate_registry_updates::<SpecTestSpec, _>(&patched, TRUSTED_EPOCH)
+                .unwrap(),
+            Err(PatchRejection::ExitNotScheduled { validator_index: 1 })
+        );
+    }
+
+    #[test]
+    fn balance_updates_change_patched_balances() {
+        let patch = StatePatch {
+            epoch: TRUSTED_EPOCH + 1,
+            effective_balance_updates: vec![balance_update(0, balance() - increment())]
+                .try_into()
+                .unwrap(),
+            ..Default::default()
+        };
+        let patched = unpatched(4);
+        assert_eq!(
+            patch
+                .validate_balance_updates::<SpecTestSpec, _>(&patched)
+                .unwrap(),
+            Ok(())
+        );
+
+        let patched = patched.with_patch(patch);
+        assert_eq!(
+            patched
+                .aggregate_validator_keys_and_balance(&[0])
+                .unwrap()
+                .1,
+            balance() - increment()
+        );
+        assert_eq!(
+            patched.get_total_active_balance(TRUSTED_EPOCH).unwrap(),
+            4 * balance() - increment()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_effective_balance() {
+        let with_balance = |effective_balance: u64| StatePatch {
+            epoch: TRUSTED_EPOCH + 1,
+            effective_balance_updates: vec![balance_update(0, effective_balance)]
+                .try_into()
+                .unwrap(),
+            ..Default::default()
+        };
+        for effective_balance in [balance() + increment(), balance() - increment() / 2] {
+            assert_eq!(
+                with_balance(effective_balance)
+                    .validate_balance_updates::<SpecTestSpec, _>(&unpatched(4))
+                    .unwrap(),
+                Err(PatchRejection::InvalidEffectiveBalance {
+                    validator_index: 0,
+                    effective_balance
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn slashed_validators_can_lose_more_than_one_increment() {
+        let mut patch = StatePatch {
+            epoch: TRUSTED_EPOCH + 2,
+            effective_balance_updates: vec![balance_update(0, balance() - 2 * increment())]
+                .try_into()
+                .unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(
+            patch
+                .validate_balance_updates::<SpecTestSpec, _>(&unpatched(4))
+                .unwrap(),
+            Err(PatchRejection::EffectiveBalanceChangeTooLarge {
+                validator_index: 0,
+                from: balance(),
+                to: balance() - 2 * increment(),
+            })
+        );
+
+        // slashed by an earlier patch
+        let slashed = unpatched(4).with_patch(StatePatch {
+            epoch: TRUSTED_EPOCH + 1,
+            slashings: vec![0].try_into().unwrap(),
+            ..Default::default()
+        });
+        assert_eq!(
+            patch
+                .validate_balance_updates::<SpecTestSpec, _>(&slashed)
+                .unwrap(),
+            Ok(())
+        );
+
+        // or by the same one
+        patch.slashings.push(0);
+        assert_eq!(
+            patch
+                .validate_balance_updates::<SpecTestSpec, _>(&unpatched(4))
+                .unwrap(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_slashing_unknown_or_already_slashed_validators() {
+        let mut state = TestStateReader::with_validators::<SpecTestSpec>(4);
+        state.validators[1].slashed = true;
+        let patched = PatchedTestStateReader::new(state);
+        let slashing = |slashings: Vec<u32>| StatePatch {
+            epoch: TRUSTED_EPOCH + 1,
+            slashings: slashings.try_into().unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(
+            slashing(vec![0, 2]).validate_slashings(&patched).unwrap(),
+            Ok(())
+        );
+        assert_eq!(
+            slashing(vec![0, 0]).validate_slashings(&patched).unwrap(),
+            Err(PatchRejection::AlreadySlashed { validator_index: 0 })
+        );
+        assert_eq!(
+            slashing(vec![1]).validate_slashings(&patched).unwrap(),
+            Err(PatchRejection::AlreadySlashed { validator_index: 1 })
+        );
+        assert_eq!(
+            slashing(vec![4]).validate_slashings(&patched).unwrap(),
+            Err(PatchRejection::UnknownValidator { validator_index: 4 })
+        );
+    }
+
+    #[test]
+    fn rejects_patch_with_invalid_new_validator_pubkey() {
+        let mut record = validator::<SpecTestSpec>(FAR_FUTURE_EPOCH);
+        let mut patch = StatePatch {
+            epoch: TRUSTED_EPOCH + 1,
+            n_deposits_processed: 1,
+            ..Default::default()
+        };
+        patch.new_validators.push(record.clone());
+        assert_eq!(patch.validate::<SpecTestSpec>(0), Ok(()));
+
+        // not the encoding of a point
+        record.pubkey = Vector::try_from(vec![0xff; 48]).unwrap();
+        patch.new_validators[0] = record;
+        assert_eq!(
+            patch.validate::<SpecTestSpec>(0),
+            Err(PatchRejection::InvalidNewValidator { deposit_index: 0 })
+        );
+    }
 }
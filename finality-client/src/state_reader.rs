@@ -1,9 +1,13 @@
-use crate::state_patch::StatePatch;
+use crate::active_set::{ActiveSet, ActiveSetHint, ActiveSetHintRejection};
+use crate::attestation::Checkpoint;
+use crate::state_patch::{StatePatch, ValidatorRecord};
 use crypto::bls::{BlsError, PublicKey};
 use crypto::hash::H256;
 use log::warn;
 use preimage_oracle::error::PreimageOracleError;
 
+use alloc::collections::btree_map::BTreeMap as Map;
+use alloc::collections::btree_set::BTreeSet as Set;
 use alloc::vec::Vec;
 use zipline_spec::Spec;
 #[derive(Debug)]
@@ -13,7 +17,12 @@ pub enum StateReadError {
     ActiveValidators,
     BlsError(BlsError),
     ValidatorRetrieval,
+    ValidatorIndexOutOfRange(usize),
+    UncompressedPubkeysLength,
+    UncompressedPubkeyMismatch(usize),
     RootUnknown,
+    BlockRootUnavailable(u64),
+    InvalidActiveSetHint(ActiveSetHintRejection),
     PreimageOracleError(PreimageOracleError),
 }
 
@@ -23,6 +32,12 @@ impl From<BlsError> for StateReadError {
     }
 }
 
+impl From<ActiveSetHintRejection> for StateReadError {
+    fn from(value: ActiveSetHintRejection) -> Self {
+        Self::InvalidActiveSetHint(value)
+    }
+}
+
 impl From<PreimageOracleError> for StateReadError {
     fn from(e: PreimageOracleError) -> Self {
         Self::PreimageOracleError(e)
@@ -39,15 +54,15 @@ pub trait StateReader {
 
     // can override if there is already a cached copy of this available
     fn get_active_validator_indices(&self, epoch: u64) -> Result<Vec<usize>, StateReadError> {
-        Ok((0_usize..self.get_validator_count()?)
-            .filter(|validator_index| {
-                // TODO: Remove this unwrap
-                let (activation, exit) = self
-                    .get_validator_activation_and_exit_epochs(*validator_index)
-                    .unwrap();
-                activation <= epoch && epoch < exit
-            })
-            .collect())
+        let mut indices = Vec::new();
+        for validator_index in 0_usize..self.get_validator_count()? {
+            let (activation, exit) =
+                self.get_validator_activation_and_exit_epochs(validator_index)?;
+            if activation <= epoch && epoch < exit {
+                indices.push(validator_index);
+            }
+        }
+        Ok(indices)
     }
 
     fn get_total_active_balance(&self, epoch: u64) -> Result<u64, StateReadError> {
@@ -55,6 +70,25 @@ pub trait StateReader {
             .map(|x| x.1)
     }
 
+    // check a hint for the active validators and their total balance at the given epoch.
+    // Readers can override this to use the hint in place of recomputing these
+    fn apply_active_set_hint(
+        &mut self,
+        epoch: u64,
+        hint: &ActiveSetHint,
+    ) -> Result<(), StateReadError> {
+        let validators = (0..self.get_validator_count()?)
+            .map(|validator_index| {
+                let (activation, exit) =
+                    self.get_validator_activation_and_exit_epochs(validator_index)?;
+                let (_, balance) = self.aggregate_validator_keys_and_balance(&[validator_index])?;
+                Ok((activation, exit, balance))
+            })
+            .collect::<Result<Vec<_>, StateReadError>>()?;
+        ActiveSet::verify(epoch, hint, validators.into_iter())?;
+        Ok(())
+    }
+
     // the the randao value for shuffling a particular epoch
     // can only look 1 epoch into the future
     fn get_randao<S: Spec>(&self, epoch: u64) -> Result<[u8; 32], StateReadError>;
@@ -70,6 +104,39 @@ pub trait StateReader {
         &self,
         validator_index: usize,
     ) -> Result<(u64, u64), StateReadError>;
+
+    fn get_validator_activation_eligibility_epoch(
+        &self,
+        validator_index: usize,
+    ) -> Result<u64, StateReadError>;
+
+    // can override if the balance can be read without loading the public key
+    fn get_validator_effective_balance(
+        &self,
+        validator_index: usize,
+    ) -> Result<u64, StateReadError> {
+        self.aggregate_validator_keys_and_balance(&[validator_index])
+            .map(|x| x.1)
+    }
+
+    fn is_validator_slashed(&self, validator_index: usize) -> Result<bool, StateReadError>;
+
+    // epoch of the finalized checkpoint recorded in the state
+    fn get_finalized_epoch(&self) -> Result<u64, StateReadError>;
+
+    fn get_slot(&self) -> Result<u64, StateReadError>;
+
+    // the justification bits as serialized in the state. Bit i is set if the checkpoint of the epoch i + 1 before
+    // the epoch of the state's slot was justified when the state last processed justification
+    fn get_justification_bits(&self) -> Result<u8, StateReadError>;
+
+    fn get_previous_justified_checkpoint(&self) -> Result<Checkpoint, StateReadError>;
+
+    fn get_current_justified_checkpoint(&self) -> Result<Checkpoint, StateReadError>;
+
+    // the root of the block at a slot before the state. The state only keeps the roots of the last
+    // SLOTS_PER_HISTORICAL_ROOT slots
+    fn get_block_root<S: Spec>(&self, slot: u64) -> Result<H256, StateReadError>;
 }
 
 /// A patched state reader adds patches to an underlying state. It also implements StateReader and
@@ -86,6 +153,60 @@ pub trait PatchedStateReader {
     fn reader(&self) -> &Self::SR;
 }
 
+// the most recent effective balance update for each validator across all the patches
+fn effective_balance_updates(patches: &[StatePatch]) -> Map<usize, u64> {
+    patches
+        .iter()
+        .flat_map(|patch| patch.effective_balance_updates.iter())
+        .map(|update| (update.validator_index as usize, update.effective_balance))
+        .collect()
+}
+
+// the validators whose activation or exit epoch can differ from the unpatched state. Validators created by patch
+// deposits are never active until a patch activates them so these are the ones activated or exited by a patch.
+// Indices outside the registry do not refer to a validator
+fn registry_changes<T: PatchedStateReader>(state_reader: &T) -> Result<Set<usize>, StateReadError> {
+    let count = state_reader.get_validator_count()?;
+    Ok(state_reader
+        .patches()
+        .iter()
+        .flat_map(|patch| patch.activations.iter().chain(patch.exits.iter()))
+        .map(|validator_index| *validator_index as usize)
+        .filter(|validator_index| *validator_index < count)
+        .collect())
+}
+
+// the record of a validator created by a patch deposit or None if the validator is in the unpatched state
+fn new_validator_record<T: PatchedStateReader>(
+    state_reader: &T,
+    validator_index: usize,
+) -> Result<Option<&ValidatorRecord>, StateReadError> {
+    if state_reader
+        .patches()
+        .iter()
+        .all(|patch| patch.new_validators.is_empty())
+    {
+        return Ok(None);
+    }
+    let mut first_index = state_reader.reader().get_validator_count()?;
+    if validator_index < first_index {
+        return Ok(None);
+    }
+    // deposits are appended to the registry in patch order
+    for patch in state_reader.patches() {
+        let n_deposits = patch.n_deposits_processed as usize;
+        if validator_index < first_index + n_deposits {
+            return patch
+                .new_validators
+                .get(validator_index - first_index)
+                .map(Some)
+                .ok_or(StateReadError::ValidatorIndexOutOfRange(validator_index));
+        }
+        first_index += n_deposits;
+    }
+    Err(StateReadError::ValidatorIndexOutOfRange(validator_index))
+}
+
 impl<T> StateReader for T
 where
     T: PatchedStateReader,
@@ -99,8 +220,49 @@ where
     }
 
     fn get_active_validator_indices(&self, epoch: u64) -> Result<Vec<usize>, StateReadError> {
-        let indices = self.reader().get_active_validator_indices(epoch);
-        indices
+        // the reader may have the unpatched active validators cached. Only the validators activated or exited by the
+        // patches can differ from it
+        let mut indices = self.reader().get_active_validator_indices(epoch)?;
+        let changed = registry_changes(self)?;
+        indices.retain(|validator_index| !changed.contains(validator_index));
+        for validator_index in changed {
+            let (activation, exit) =
+                self.get_validator_activation_and_exit_epochs(validator_index)?;
+            if activation <= epoch && epoch < exit {
+                indices.push(validator_index);
+            }
+        }
+        indices.sort_unstable();
+        Ok(indices)
+    }
+
+    fn get_total_active_balance(&self, epoch: u64) -> Result<u64, StateReadError> {
+        // the reader may be able to compute this without loading public keys
+        let mut total = self.reader().get_total_active_balance(epoch)?;
+        // recount the validators whose activity or balance the patches changed
+        let mut changed = registry_changes(self)?;
+        changed.extend(effective_balance_updates(self.patches()).into_keys());
+        let base_count = self.reader().get_validator_count()?;
+        for validator_index in changed {
+            if validator_index < base_count {
+                let (activation, exit) = self
+                    .reader()
+                    .get_validator_activation_and_exit_epochs(validator_index)?;
+                if activation <= epoch && epoch < exit {
+                    total = total.saturating_sub(
+                        self.reader()
+                            .get_validator_effective_balance(validator_index)?,
+                    );
+                }
+            }
+            let (activation, exit) =
+                self.get_validator_activation_and_exit_epochs(validator_index)?;
+            if activation <= epoch && epoch < exit {
+                total =
+                    total.saturating_add(self.get_validator_effective_balance(validator_index)?);
+            }
+        }
+        Ok(total)
     }
 
     fn get_validator_count(&self) -> Result<usize, StateReadError> {
@@ -135,23 +297,131 @@ where
         &self,
         indices: &[usize],
     ) -> Result<(Vec<PublicKey>, u64), StateReadError> {
-        // pass straight to the reader. Patch cannot change this
-        self.reader().aggregate_validator_keys_and_balance(indices)
+        // validators created by patch deposits are read from their records, all others from the reader
+        let mut new_records = Vec::new();
+        let mut base_indices = Vec::with_capacity(indices.len());
+        for validator_index in indices {
+            match new_validator_record(self, *validator_index)? {
+                Some(record) => new_records.push(record),
+                None => base_indices.push(*validator_index),
+            }
+        }
+        let (mut keys, mut balance) = self
+            .reader()
+            .aggregate_validator_keys_and_balance(&base_indices)?;
+        for record in new_records {
+            keys.push(PublicKey::from_bytes(&record.pubkey)?);
+            balance = balance.saturating_add(record.effective_balance);
+        }
+        let updates = effective_balance_updates(self.patches());
+        if !updates.is_empty() {
+            for validator_index in indices {
+                if let Some(effective_balance) = updates.get(validator_index) {
+                    let old_balance = self
+                        .reader()
+                        .get_validator_effective_balance(*validator_index)?;
+                    balance = balance
+                        .saturating_sub(old_balance)
+                        .saturating_add(*effective_balance);
+                }
+            }
+        }
+        Ok((keys, balance))
+    }
+
+    fn get_validator_effective_balance(
+        &self,
+        validator_index: usize,
+    ) -> Result<u64, StateReadError> {
+        if let Some(effective_balance) =
+            effective_balance_updates(self.patches()).get(&validator_index)
+        {
+            return Ok(*effective_balance);
+        }
+        match new_validator_record(self, validator_index)? {
+            Some(record) => Ok(record.effective_balance),
+            None => self
+                .reader()
+                .get_validator_effective_balance(validator_index),
+        }
+    }
+
+    fn get_validator_activation_eligibility_epoch(
+        &self,
+        validator_index: usize,
+    ) -> Result<u64, StateReadError> {
+        // patches do not record eligibility changes
+        match new_validator_record(self, validator_index)? {
+            Some(record) => Ok(record.activation_eligibility_epoch),
+            None => self
+                .reader()
+                .get_validator_activation_eligibility_epoch(validator_index),
+        }
+    }
+
+    fn is_validator_slashed(&self, validator_index: usize) -> Result<bool, StateReadError> {
+        // validators can never be unslashed so a patch can only add to the slashed validators
+        let newly_slashed = u32::try_from(validator_index).is_ok_and(|patch_index| {
+            self.patches()
+                .iter()
+                .any(|patch| patch.slashings.contains(&patch_index))
+        });
+        if newly_slashed {
+            return Ok(true);
+        }
+        match new_validator_record(self, validator_index)? {
+            Some(record) => Ok(record.slashed),
+            None => self.reader().is_validator_slashed(validator_index),
+        }
+    }
+
+    // patches do not record finality so this is the finalized epoch of the unpatched state.
+    // As finality only advances this is a lower bound for the patched state
+    fn get_finalized_epoch(&self) -> Result<u64, StateReadError> {
+        self.reader().get_finalized_epoch()
+    }
+
+    // as with finality the justification fields and block roots are those of the unpatched state
+    fn get_slot(&self) -> Result<u64, StateReadError> {
+        self.reader().get_slot()
+    }
+
+    fn get_justification_bits(&self) -> Result<u8, StateReadError> {
+        self.reader().get_justification_bits()
+    }
+
+    fn get_previous_justified_checkpoint(&self) -> Result<Checkpoint, StateReadError> {
+        self.reader().get_previous_justified_checkpoint()
+    }
+
+    fn get_current_justified_checkpoint(&self) -> Result<Checkpoint, StateReadError> {
+        self.reader().get_current_justified_checkpoint()
+    }
+
+    fn get_block_root<S: Spec>(&self, slot: u64) -> Result<H256, StateReadError> {
+        self.reader().get_block_root::<S>(slot)
     }
 
     fn get_validator_activation_and_exit_epochs(
         &self,
         validator_index: usize,
     ) -> Result<(u64, u64), StateReadError> {
-        let (mut activation, mut exit) = self
-            .reader()
-            .get_validator_activation_and_exit_epochs(validator_index)?;
+        let (mut activation, mut exit) = match new_validator_record(self, validator_index)? {
+            Some(record) => (record.activation_epoch, record.exit_epoch),
+            None => self
+                .reader()
+                .get_validator_activation_and_exit_epochs(validator_index)?,
+        };
+        // patches can only refer to validators with indices that fit in a u32
+        let Ok(patch_index) = u32::try_from(validator_index) else {
+            return Ok((activation, exit));
+        };
         // replace any activations/exists with their most recent patch updates if any
         for patch in self.patches() {
             if patch
                 .activations
                 .iter()
-                .filter(|vi| **vi == validator_index.try_into().unwrap())
+                .filter(|vi| **vi == patch_index)
                 .last()
                 .is_some()
             {
@@ -166,7 +436,7 @@ where
             if patch
                 .exits
                 .iter()
-                .filter(|vi| **vi == validator_index.try_into().unwrap())
+                .filter(|vi| **vi == patch_index)
                 .last()
                 .is_some()
             {
@@ -183,3 +453,119 @@ where
         Ok((activation, exit))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::active_set::FAR_FUTURE_EPOCH;
+    use crate::test_state_reader::{validator, PatchedTestStateReader, TestStateReader};
+    use alloc::vec;
+    use zipline_spec::SpecTestSpec;
+
+    #[test]
+    fn patched_active_validators_include_patch_activations_and_exits() {
+        let balance = SpecTestSpec::max_effective_balance();
+        let mut state = TestStateReader::with_validators::<SpecTestSpec>(4);
+        state
+            .validators
+            .push(validator::<SpecTestSpec>(FAR_FUTURE_EPOCH));
+        let mut deposit = StatePatch {
+            epoch: 11,
+            n_deposits_processed: 1,
+            ..Default::default()
+        };
+        deposit
+            .new_validators
+            .push(validator::<SpecTestSpec>(FAR_FUTURE_EPOCH));
+        let patched = PatchedTestStateReader::new(state)
+            .with_patch(StatePatch {
+                epoch: 10,
+                activations: vec![4].try_into().unwrap(),
+                exits: vec![1].try_into().unwrap(),
+                ..Default::default()
+            })
+            .with_patch(deposit)
+            .with_patch(StatePatch {
+                epoch: 12,
+                activations: vec![5].try_into().unwrap(),
+                ..Default::default()
+            });
+
+        assert_eq!(
+            patched.get_active_validator_indices(9).unwrap(),
+            vec![0, 1, 2, 3]
+        );
+        assert_eq!(
+            patched.get_active_validator_indices(10).unwrap(),
+            vec![0, 2, 3, 4]
+        );
+        assert_eq!(
+            patched.get_active_validator_indices(12).unwrap(),
+            vec![0, 2, 3, 4, 5]
+        );
+        // the total is kept consistent with the active validators
+        for epoch in [9, 10, 12] {
+            let active = patched.get_active_validator_indices(epoch).unwrap();
+            assert_eq!(
+                patched.get_total_active_balance(epoch).unwrap(),
+                active.len() as u64 * balance
+            );
+        }
+    }
+
+    #[test]
+    fn slashed_validators_stay_active() {
+        let patched =
+            PatchedTestStateReader::new(TestStateReader::with_validators::<SpecTestSpec>(4))
+                .with_patch(StatePatch {
+                    epoch: 11,
+                    slashings: vec![0].try_into().unwrap(),
+                    ..Default::default()
+                });
+        assert!(patched.is_validator_slashed(0).unwrap());
+        assert!(!patched.is_validator_slashed(1).unwrap());
+        assert_eq!(
+            patched.get_active_validator_indices(11).unwrap(),
+            vec![0, 1, 2, 3]
+        );
+        assert_eq!(
+            patched.get_total_active_balance(11).unwrap(),
+            4 * SpecTestSpec::max_effective_balance()
+        );
+    }
+
+    #[test]
+    fn new_validators_are_readable_from_patched_state() {
+        let mut record = validator::<SpecTestSpec>(FAR_FUTURE_EPOCH);
+        record.activation_eligibility_epoch = 11;
+        record.effective_balance -= SpecTestSpec::effective_balance_increment();
+        let mut deposit = StatePatch {
+            epoch: 11,
+            n_deposits_processed: 1,
+            ..Default::default()
+        };
+        deposit.new_validators.push(record.clone());
+        let patched =
+            PatchedTestStateReader::new(TestStateReader::with_validators::<SpecTestSpec>(4))
+                .with_patch(deposit);
+
+        assert_eq!(patched.get_validator_count().unwrap(), 5);
+        let (keys, balance) = patched.aggregate_validator_keys_and_balance(&[4]).unwrap();
+        assert_eq!(keys[0].to_bytes().as_slice(), record.pubkey.as_ref());
+        assert_eq!(balance, record.effective_balance);
+        assert_eq!(
+            patched
+                .get_validator_activation_eligibility_epoch(4)
+                .unwrap(),
+            11
+        );
+        assert_eq!(
+            patched.get_validator_activation_and_exit_epochs(4).unwrap(),
+            (FAR_FUTURE_EPOCH, FAR_FUTURE_EPOCH)
+        );
+        assert!(matches!(
+            patched.get_validator_activation_and_exit_epochs(5),
+            Err(StateReadError::ValidatorIndexOutOfRange(5))
+        ));
+    }
+}
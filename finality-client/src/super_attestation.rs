@@ -0,0 +1,83 @@
+//! A super attestation combines many attestations into one container with a single signature. Attestations with the same
+//! data (other than the committee index) are grouped and the checkpoints are stored once and referred to by id.
+//! The signature is the aggregate of all the committee signatures so it can be checked with a single multi-pairing
+//! rather than one pairing check per committee. This must match the format produced by the preprocessor.
+
+use crate::attestation::{AttestationData, Checkpoint};
+use alloc::{vec, vec::Vec};
+use crypto::bls::BLS_SIGNATURE_BYTES_LEN;
+use crypto::hash::H256;
+use ssz_rs::prelude::*;
+
+pub const MAX_CHECKPOINTS: usize = 1024;
+
+#[derive(Clone, Debug, Default, SimpleSerialize, PartialEq)]
+pub struct CompressedAttestationData {
+    pub slot: u64,
+    pub beacon_block_root: H256,
+    pub source: u32, // these are indices into the checkpoints list
+    pub target: u32, // in the root SuperAttestation
+}
+
+#[derive(Clone, Debug, Default, SimpleSerialize, PartialEq)]
+pub struct Participation<const MAX_COMMITTEE_SIZE: usize> {
+    pub bitlist: Bitlist<MAX_COMMITTEE_SIZE>,
+    pub committee_index: u64,
+}
+
+#[derive(Clone, Debug, Default, SimpleSerialize, PartialEq)]
+pub struct CompressedAttestation<const MAX_COMMITTEE_SIZE: usize, const MAX_PARTICIPATIONS: usize> {
+    pub data: CompressedAttestationData,
+    pub participation: List<Participation<MAX_COMMITTEE_SIZE>, MAX_PARTICIPATIONS>,
+}
+
+#[derive(Clone, Debug, Default, SimpleSerialize, PartialEq)]
+pub struct SuperAttestation<const MAX_COMMITTEE_SIZE: usize, const MAX_ATTESTATIONS: usize> {
+    pub attestations:
+        List<CompressedAttestation<MAX_COMMITTEE_SIZE, MAX_ATTESTATIONS>, MAX_ATTESTATIONS>,
+    pub checkpoints: List<Checkpoint, MAX_CHECKPOINTS>,
+    pub signature: Vector<u8, BLS_SIGNATURE_BYTES_LEN>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SuperAttestationError {
+    UnknownCheckpointId(u32),
+}
+
+impl<const MAX_COMMITTEE_SIZE: usize, const MAX_ATTESTATIONS: usize>
+    SuperAttestation<MAX_COMMITTEE_SIZE, MAX_ATTESTATIONS>
+{
+    pub fn is_empty(&self) -> bool {
+        self.attestations.is_empty()
+    }
+
+    /// The attestation data and participation bits for every committee included in the super attestation
+    pub fn committee_attestations(
+        &self,
+    ) -> Result<Vec<(AttestationData, &Bitlist<MAX_COMMITTEE_SIZE>)>, SuperAttestationError> {
+        let checkpoint = |id: u32| {
+            self.checkpoints
+                .get(id as usize)
+                .copied()
+                .ok_or(SuperAttestationError::UnknownCheckpointId(id))
+        };
+        let mut committee_attestations = Vec::new();
+        for a in self.attestations.iter() {
+            let source = checkpoint(a.data.source)?;
+            let target = checkpoint(a.data.target)?;
+            for p in a.participation.iter() {
+                committee_attestations.push((
+                    AttestationData {
+                        slot: a.data.slot,
+                        index: p.committee_index,
+                        beacon_block_root: a.data.beacon_block_root,
+                        source,
+                        target,
+                    },
+                    &p.bitlist,
+                ));
+            }
+        }
+        Ok(committee_attestations)
+    }
+}
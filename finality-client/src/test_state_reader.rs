@@ -0,0 +1,161 @@
+//! A state reader over a small in-memory validator registry for unit tests.
+
+use crate::active_set::FAR_FUTURE_EPOCH;
+use crate::attestation::Checkpoint;
+use crate::state_patch::{StatePatch, ValidatorRecord};
+use crate::state_reader::{PatchedStateReader, StateReadError, StateReader};
+use alloc::vec::Vec;
+use crypto::bls::PublicKey;
+use crypto::hash::H256;
+use ssz_rs::Vector;
+use zipline_spec::Spec;
+
+// the compressed G1 generator. A valid public key that every test validator shares
+const PUBKEY: &str = "97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb";
+
+/// A validator record that is eligible and active from `activation_epoch` with the max effective balance
+pub(crate) fn validator<S: Spec>(activation_epoch: u64) -> ValidatorRecord {
+    let mut pubkey = [0u8; 48];
+    hex::decode_to_slice(PUBKEY, &mut pubkey).unwrap();
+    ValidatorRecord {
+        pubkey: Vector::try_from(pubkey.to_vec()).unwrap(),
+        effective_balance: S::max_effective_balance(),
+        activation_eligibility_epoch: activation_epoch
+            .saturating_sub(S::max_seed_lookahead() as u64 + 1),
+        activation_epoch,
+        exit_epoch: FAR_FUTURE_EPOCH,
+        withdrawable_epoch: FAR_FUTURE_EPOCH,
+        ..Default::default()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct TestStateReader {
+    pub validators: Vec<ValidatorRecord>,
+    pub finalized_epoch: u64,
+}
+
+impl TestStateReader {
+    /// A registry of `n` validators active from genesis
+    pub fn with_validators<S: Spec>(n: usize) -> Self {
+        Self {
+            validators: (0..n).map(|_| validator::<S>(0)).collect(),
+            finalized_epoch: 0,
+        }
+    }
+
+    fn validator(&self, validator_index: usize) -> Result<&ValidatorRecord, StateReadError> {
+        self.validators
+            .get(validator_index)
+            .ok_or(StateReadError::ValidatorIndexOutOfRange(validator_index))
+    }
+}
+
+impl StateReader for TestStateReader {
+    fn root(&self) -> Result<H256, StateReadError> {
+        Err(StateReadError::RootUnknown)
+    }
+
+    fn get_validator_count(&self) -> Result<usize, StateReadError> {
+        Ok(self.validators.len())
+    }
+
+    fn get_randao<S: Spec>(&self, _epoch: u64) -> Result<[u8; 32], StateReadError> {
+        Ok([0; 32])
+    }
+
+    fn aggregate_validator_keys_and_balance(
+        &self,
+        indices: &[usize],
+    ) -> Result<(Vec<PublicKey>, u64), StateReadError> {
+        let mut keys = Vec::with_capacity(indices.len());
+        let mut balance = 0;
+        for validator_index in indices {
+            let validator = self.validator(*validator_index)?;
+            keys.push(PublicKey::from_bytes(&validator.pubkey)?);
+            balance += validator.effective_balance;
+        }
+        Ok((keys, balance))
+    }
+
+    fn get_validator_activation_and_exit_epochs(
+        &self,
+        validator_index: usize,
+    ) -> Result<(u64, u64), StateReadError> {
+        let validator = self.validator(validator_index)?;
+        Ok((validator.activation_epoch, validator.exit_epoch))
+    }
+
+    fn get_validator_activation_eligibility_epoch(
+        &self,
+        validator_index: usize,
+    ) -> Result<u64, StateReadError> {
+        Ok(self
+            .validator(validator_index)?
+            .activation_eligibility_epoch)
+    }
+
+    fn get_validator_effective_balance(
+        &self,
+        validator_index: usize,
+    ) -> Result<u64, StateReadError> {
+        Ok(self.validator(validator_index)?.effective_balance)
+    }
+
+    fn is_validator_slashed(&self, validator_index: usize) -> Result<bool, StateReadError> {
+        Ok(self.validator(validator_index)?.slashed)
+    }
+
+    fn get_finalized_epoch(&self) -> Result<u64, StateReadError> {
+        Ok(self.finalized_epoch)
+    }
+
+    fn get_slot(&self) -> Result<u64, StateReadError> {
+        Ok(0)
+    }
+
+    fn get_justification_bits(&self) -> Result<u8, StateReadError> {
+        Ok(0)
+    }
+
+    fn get_previous_justified_checkpoint(&self) -> Result<Checkpoint, StateReadError> {
+        Ok(Checkpoint::default())
+    }
+
+    fn get_current_justified_checkpoint(&self) -> Result<Checkpoint, StateReadError> {
+        Ok(Checkpoint::default())
+    }
+
+    fn get_block_root<S: Spec>(&self, slot: u64) -> Result<H256, StateReadError> {
+        Err(StateReadError::BlockRootUnavailable(slot))
+    }
+}
+
+pub(crate) struct PatchedTestStateReader {
+    state_reader: TestStateReader,
+    patches: Vec<StatePatch>,
+}
+
+impl PatchedStateReader for PatchedTestStateReader {
+    type SR = TestStateReader;
+
+    fn new(state_reader: Self::SR) -> Self {
+        Self {
+            state_reader,
+            patches: Vec::new(),
+        }
+    }
+
+    fn with_patch(mut self, patch: StatePatch) -> Self {
+        self.patches.push(patch);
+        self
+    }
+
+    fn patches(&self) -> &[StatePatch] {
+        &self.patches
+    }
+
+    fn reader(&self) -> &Self::SR {
+        &self.state_reader
+    }
+}
@@ -4,11 +4,22 @@ use ssz_rs::{is_valid_merkle_branch, Node};
 use validator_shuffling::{committee_shuffle_seed_from_randao, CommitteeCache, ShuffleData};
 use zipline_spec::Spec;
 
-use crate::attestation::{Attestation, CasperLink};
+use crate::active_set::ActiveSetHintRejection;
+use crate::ancestry::AncestryRejection;
+use crate::attestation::{
+    get_attesting_indices_from_bits, Attestation, AttestationData, AttestationRejection,
+    CasperLink, Checkpoint,
+};
 use crate::input::ZiplineInput;
-use crate::signing::verify_signed_attestation;
-use crate::state_patch::StatePatch;
+use crate::signing::{
+    attestation_signing_root, find_invalid_attestation_signatures,
+    verify_super_attestation_signature, SigningError,
+};
+use crate::state_patch::{patch_influence, PatchInfluence, PatchRejection, StatePatch};
 use crate::state_reader::{StateReadError, StateReader};
+use crate::super_attestation::SuperAttestationError;
+use crypto::bls::{BlsError, PublicKey, Signature};
+use ssz_rs::Bitlist;
 
 use alloc::collections::btree_map::BTreeMap as Map;
 use alloc::collections::btree_set::BTreeSet as Set;
@@ -17,6 +28,24 @@ use crate::state_reader::PatchedStateReader;
 
 use alloc::vec::Vec;
 
+/// Number of consecutive epochs tracked when justifying checkpoints, as in the beacon chain spec.
+/// A candidate can be finalized by k-finality for any k < JUSTIFICATION_BITS_LENGTH, see `k_finality_links`
+pub const JUSTIFICATION_BITS_LENGTH: u64 = 4;
+
+/// Optional limits applied by `verify_with_config`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VerifyConfig {
+    /// Reject inputs where the patches could have inserted or removed more than this fraction of the
+    /// trusted total active balance, in basis points. See `PatchInfluence`
+    pub max_patch_influence_bps: Option<u64>,
+    /// Also check patch activations and exits against the registry update rules. See `StatePatch::validate_registry_updates`
+    pub strict_patches: bool,
+    /// Also check the slot and justification of the trusted state are consistent with the trusted checkpoint.
+    /// See `check_trusted_state`
+    pub check_trusted_state: bool,
+}
+
+/// Verify with the default configuration which applies no additional limits
 pub fn verify<
     S: Spec,
     PSR: PatchedStateReader,
@@ -25,8 +54,26 @@ pub fn verify<
     const MAX_PATCHES: usize,
 >(
     state_reader: PSR::SR,
+    input: ZiplineInput<MAX_COMMITTEE_SIZE, MAX_ATTESTATIONS, MAX_PATCHES>,
+) -> Result<VerificationOutcome, Error> {
+    verify_with_config::<S, PSR, MAX_COMMITTEE_SIZE, MAX_ATTESTATIONS, MAX_PATCHES>(
+        state_reader,
+        input,
+        &VerifyConfig::default(),
+    )
+}
+
+pub fn verify_with_config<
+    S: Spec,
+    PSR: PatchedStateReader,
+    const MAX_COMMITTEE_SIZE: usize,
+    const MAX_ATTESTATIONS: usize,
+    const MAX_PATCHES: usize,
+>(
+    mut state_reader: PSR::SR,
     mut input: ZiplineInput<MAX_COMMITTEE_SIZE, MAX_ATTESTATIONS, MAX_PATCHES>,
-) -> Result<bool, Error> {
+    config: &VerifyConfig,
+) -> Result<VerificationOutcome, Error> {
     let trusted_cp = input.trusted_cp;
     let candidate_cp = input.candidate_cp;
     log::info!("Verify Start!");
@@ -37,45 +84,103 @@ pub fn verify<
     );
     log::debug!("0. Checking pre-conditions");
     /////////// 0. pre-conditions  //////////////
-    assert_eq!(
-        candidate_cp.epoch,
-        trusted_cp.epoch + 1,
-        "Candidate must be direct successor of trusted checkpoint"
-    );
-    assert!(
-        !input.patches.is_empty(),
-        "Must be at least one patch to finalize candidate"
-    );
-    assert_eq!(
-        input.patches[0].epoch, candidate_cp.epoch,
-        "first patch must produce the state as of the candidate"
-    );
-    assert!(
-        contiguous_patches(&input.patches),
-        "Patches must be of contiguous, incrementing epochs"
-    );
-    assert!(
-        is_valid_merkle_branch(
-            &node_from_hash(&state_reader.root()?),
-            input
-                .state_proof
-                .iter()
-                .map(node_from_hash)
-                .collect::<Vec<_>>()
-                .iter(),
-            3,  // depth of BeaconBlockHeader merklizations
-            11, // gindex of state_root in beacon block header
-            &node_from_hash(&trusted_cp.root),
-        ),
-        "Given state root must correspond to the trusted_cp as shown by proof"
-    );
+    if candidate_cp.epoch <= trusted_cp.epoch {
+        return Err(PreconditionError::CandidateNotAfterTrusted.into());
+    }
+    let Some(first_patch) = input.patches.first() else {
+        return Err(PreconditionError::NoPatches.into());
+    };
+    if first_patch.epoch != trusted_cp.epoch + 1 {
+        // first patch must produce the state as of the epoch following the trusted checkpoint
+        return Err(PreconditionError::FirstPatchEpoch {
+            expected: trusted_cp.epoch + 1,
+            found: first_patch.epoch,
+        }
+        .into());
+    }
+    if (input.patches.len() as u64) < candidate_cp.epoch - trusted_cp.epoch {
+        return Err(PreconditionError::PatchesEndBeforeCandidate.into());
+    }
+    if !contiguous_patches(&input.patches) {
+        return Err(PreconditionError::NonContiguousPatches.into());
+    }
+    // the patched states are used to verify attestations for the epoch following them
+    // so the epoch after the last patch must also be representable
+    let Some(last_epoch) = trusted_cp.epoch.checked_add(input.patches.len() as u64) else {
+        return Err(PreconditionError::EpochOverflow.into());
+    };
+    if last_epoch == u64::MAX {
+        return Err(PreconditionError::EpochOverflow.into());
+    }
+    // attestations are verified up to the epoch after the last patch. Their Electra container and the Electra
+    // churn rules are not implemented so those epochs must all be before Electra
+    let fork = S::fork_at_epoch(last_epoch + 1);
+    if fork.electra_or_later {
+        return Err(PreconditionError::UnsupportedFork {
+            fork_epoch: fork.epoch,
+        }
+        .into());
+    }
+    // Given state root must correspond to the trusted_cp as shown by proof
+    if !is_valid_merkle_branch(
+        &node_from_hash(&state_reader.root()?),
+        input
+            .state_proof
+            .iter()
+            .map(node_from_hash)
+            .collect::<Vec<_>>()
+            .iter(),
+        3,  // depth of BeaconBlockHeader merklizations
+        11, // gindex of state_root in beacon block header
+        &node_from_hash(&trusted_cp.root),
+    ) {
+        return Err(PreconditionError::InvalidStateProof.into());
+    }
+    if config.check_trusted_state {
+        if let Err(reason) = check_trusted_state::<S, _>(&state_reader, trusted_cp)? {
+            return Err(PreconditionError::InconsistentTrustedState(reason).into());
+        }
+    }
+    // Any block roots to be finalized along with the candidate must be its ancestors
+    if let Err(reason) = input.candidate_ancestry.validate::<S>(&candidate_cp) {
+        return Err(PreconditionError::InvalidAncestry(reason).into());
+    }
+    // Hints for the active validators in the trusted epoch are checked once and reused for every later epoch
+    if !input.active_set_hint.is_empty() {
+        match state_reader.apply_active_set_hint(trusted_cp.epoch, &input.active_set_hint) {
+            Err(StateReadError::InvalidActiveSetHint(reason)) => {
+                return Err(PreconditionError::InvalidActiveSetHint(reason).into());
+            }
+            result => result?,
+        }
+    }
+
+    // The patches can only shift the validator set by a bounded amount. Reject them if that exceeds the configured limit
+    if let Some(max_basis_points) = config.max_patch_influence_bps {
+        let influence = patch_influence::<S, _>(&state_reader, trusted_cp.epoch, &input.patches)?;
+        log::info!(
+            "Patches could have changed {} bps of the active balance",
+            influence.basis_points()
+        );
+        if influence.basis_points() > max_basis_points {
+            warn!("Patch influence exceeds limit of {} bps", max_basis_points);
+            return Ok(VerificationOutcome::PatchInfluenceExceeded {
+                influence,
+                max_basis_points,
+            });
+        }
+    }
+
+    // Justification recorded in the trusted state is read before patching as patches do not record it
+    let trusted_justified = trusted_justified_checkpoints::<S, _>(&state_reader, trusted_cp)?;
+    trace!("Justified in trusted state: {:?}", trusted_justified);
 
     /////////// 1. Attestation processing  //////////////
     log::debug!("1. Attestation processing start");
     let mut state_reader = PSR::new(state_reader);
 
     // these are the epochs we have states (or can patch state) for
-    let epoch_range = trusted_cp.epoch..=trusted_cp.epoch + (input.patches.len() as u64);
+    let epoch_range = trusted_cp.epoch..=last_epoch;
     let patches = [None] // no need to patch first epoch
         .into_iter()
         .chain(input.patches.iter().map(Some));
@@ -83,14 +188,56 @@ pub fn verify<
     // how much attested balance we have for each link found so far
     // in attestations with valid signatures
     let mut attested_balance_by_link = Map::<CasperLink, u64>::new();
+    // the validators whose balance has been counted for each target epoch. The same aggregate can be submitted more
+    // than once and aggregates for a committee can overlap. A validator also only gets one vote per target epoch,
+    // voting again with a different source or target is a slashable double vote, so only its first vote adds balance
+    let mut attesters_by_target = Map::<u64, Set<usize>>::new();
+    // the balance a link needs to be a supermajority link, by target epoch. As in the beacon chain this is 2/3 of
+    // the total active balance of the target epoch
+    let mut required_balance_by_target = Map::<u64, u64>::new();
+
+    // the committee attestations in the super attestation share a single signature. This is verified after
+    // the loop once the signing root and aggregate key for every committee is known
+    let mut super_committee_attestations = input.super_attestation.committee_attestations()?;
+    let mut super_signing_roots = Vec::new();
+    let mut super_public_keys = Vec::new();
+    let mut super_attester_balances = Vec::new();
     for (epoch, patch) in epoch_range.zip(patches) {
         log::info!("Loop epoch: {}", epoch);
         // patch the state reader if required
         if let Some(patch) = patch {
             trace!("Checking patch validity");
-            if !patch.is_valid::<S>(state_reader.get_active_validator_indices(epoch)?.len() as u32)
+            if let Err(reason) =
+                patch.validate::<S>(state_reader.get_active_validator_indices(epoch)?.len() as u32)
             {
-                return Ok(false);
+                warn!("Patch for epoch {} rejected: {:?}", patch.epoch, reason);
+                return Ok(VerificationOutcome::PatchRejected {
+                    epoch: patch.epoch,
+                    reason,
+                });
+            }
+            // balance updates and slashings change attesting weight so are always checked
+            let weight_changes = match patch.validate_balance_updates::<S, _>(&state_reader)? {
+                Ok(()) => patch.validate_slashings(&state_reader)?,
+                rejected => rejected,
+            };
+            if let Err(reason) = weight_changes {
+                warn!("Patch for epoch {} rejected: {:?}", patch.epoch, reason);
+                return Ok(VerificationOutcome::PatchRejected {
+                    epoch: patch.epoch,
+                    reason,
+                });
+            }
+            if config.strict_patches {
+                if let Err(reason) =
+                    patch.validate_registry_updates::<S, _>(&state_reader, trusted_cp.epoch)?
+                {
+                    warn!("Patch for epoch {} rejected: {:?}", patch.epoch, reason);
+                    return Ok(VerificationOutcome::PatchRejected {
+                        epoch: patch.epoch,
+                        reason,
+                    });
+                }
             }
             trace!("Applying patch to state");
             trace!(
@@ -105,46 +252,110 @@ pub fn verify<
         let attestations_epoch = epoch + 1;
 
         let committee_cache = get_shufflings_for_epoch::<S, _>(&state_reader, attestations_epoch)?;
+        // attestations in this epoch must have it as their target, see `AttestationData::validate`
+        required_balance_by_target.insert(
+            attestations_epoch,
+            supermajority_balance(state_reader.get_total_active_balance(attestations_epoch)?),
+        );
 
         let epoch_attestations = input
             .attestations
             .iter_mut()
             .filter(|a| S::epoch(a.data.slot as usize) as u64 == attestations_epoch);
+        // the signatures for the epoch are verified together as one batch once every attestation is collected
+        let mut batch_signing_roots = Vec::new();
+        let mut batch_public_keys = Vec::new();
+        let mut batch_signatures = Vec::new();
+        let mut batch_attester_balances = Vec::new();
         for a in epoch_attestations {
             trace!(
                 "Checking attestation for slot: {} committee: {}",
                 a.data.slot,
                 a.data.index
             );
-            let committee = committee_cache
-                .get_beacon_committee::<S>(a.data.slot as usize, a.data.index as usize)
-                .unwrap();
-            let participants = get_attesting_indices(committee, a);
-            trace!(
-                "Attestations has {}/{} participants",
-                participants.len(),
-                committee.len()
-            );
-
-            let (pubkeys, attesting_balance) = state_reader
-                .aggregate_validator_keys_and_balance(&participants)
-                .unwrap();
-            log::trace!("Verifying signed attestations");
-
-            match verify_signed_attestation::<S, MAX_COMMITTEE_SIZE>(a, &pubkeys) {
-                Ok(_) => {
-                    trace!("Signature ok!");
-                    if let Some(val) = attested_balance_by_link.get_mut(&a.data.link()) {
-                        *val += attesting_balance;
-                    } else {
-                        attested_balance_by_link.insert(a.data.link(), attesting_balance);
-                    }
+            let participants = match validate_attestation::<S, MAX_COMMITTEE_SIZE>(
+                &committee_cache,
+                &a.data,
+                &a.aggregation_bits,
+            )? {
+                Ok(participants) => participants,
+                Err(reason) => {
+                    warn!("Malformed attestation skipped: {:?}", reason);
+                    continue;
                 }
+            };
+            trace!("Attestations has {} participants", participants.len());
+
+            let signature = match Signature::from_bytes(&a.signature) {
+                Ok(signature) => signature,
                 Err(e) => {
                     warn!("Invalid attestation signature found: {:?}", e);
                     warn!("Attesting indices: {:?}", participants);
+                    continue;
                 }
+            };
+            let (pubkeys, _) = state_reader.aggregate_validator_keys_and_balance(&participants)?;
+            batch_public_keys.push(PublicKey::aggregate(&pubkeys)?);
+            batch_signing_roots.push(attestation_signing_root::<S>(&mut a.data)?);
+            batch_signatures.push(signature);
+            batch_attester_balances.push((
+                a.data.link(),
+                attester_balances(&state_reader, &participants)?,
+            ));
+        }
+
+        log::trace!(
+            "Verifying {} signed attestations",
+            batch_attester_balances.len()
+        );
+        let invalid = find_invalid_attestation_signatures(
+            &batch_signing_roots,
+            &batch_public_keys,
+            &batch_signatures,
+        )?;
+        for (i, (link, balances)) in batch_attester_balances.into_iter().enumerate() {
+            if invalid.contains(&i) {
+                warn!("Invalid attestation signature found for link: {:?}", link);
+                continue;
             }
+            add_attested_balance(
+                &mut attested_balance_by_link,
+                &mut attesters_by_target,
+                link,
+                &balances,
+            );
+        }
+
+        let epoch_super_committee_attestations = super_committee_attestations
+            .iter_mut()
+            .filter(|(data, _)| S::epoch(data.slot as usize) as u64 == attestations_epoch);
+        for (data, bits) in epoch_super_committee_attestations {
+            // skipping a malformed committee attestation leaves its signer out of the aggregate key
+            // so the super attestation signature will not verify
+            let participants = match validate_attestation::<S, MAX_COMMITTEE_SIZE>(
+                &committee_cache,
+                data,
+                bits,
+            )? {
+                Ok(participants) => participants,
+                // contributes nothing to the aggregate signature
+                Err(AttestationRejection::NoParticipants) => continue,
+                Err(reason) => {
+                    warn!(
+                        "Malformed super attestation committee skipped: {:?}",
+                        reason
+                    );
+                    continue;
+                }
+            };
+            let (pubkeys, _) = state_reader.aggregate_validator_keys_and_balance(&participants)?;
+            super_public_keys.push(PublicKey::aggregate(&pubkeys)?);
+            super_signing_roots.push(attestation_signing_root::<S>(data)?);
+            // balances are read now as the state reader is patched for later epochs as the loop continues
+            super_attester_balances.push((
+                data.link(),
+                attester_balances(&state_reader, &participants)?,
+            ));
         }
 
         trace!(
@@ -153,73 +364,278 @@ pub fn verify<
         );
     }
 
+    if !super_attester_balances.is_empty() {
+        log::trace!(
+            "Verifying super attestation signature over {} committees",
+            super_attester_balances.len()
+        );
+        match verify_super_attestation_signature(
+            &super_signing_roots,
+            &super_public_keys,
+            &input.super_attestation.signature,
+        ) {
+            Ok(_) => {
+                trace!("Super attestation signature ok!");
+                for (link, balances) in super_attester_balances {
+                    add_attested_balance(
+                        &mut attested_balance_by_link,
+                        &mut attesters_by_target,
+                        link,
+                        &balances,
+                    );
+                }
+            }
+            Err(e) => {
+                warn!("Invalid super attestation signature found: {:?}", e);
+            }
+        }
+    }
+
     /////////// 2. Finality calculation  //////////////
     log::debug!("2. Finality calculation start");
     // ok now we have verified all the attestations signatures we can and aggregated the attesting balance
     // for each link across all epoch. The resulting map between links and attesting balance can be used to
     // calculate the supermajority links that we have
 
-    let sm_links = get_supermajority_links::<PSR, MAX_COMMITTEE_SIZE>(
-        &state_reader,
-        trusted_cp.epoch,
-        &attested_balance_by_link,
-    )?;
-
-    // Because by definition the trusted CP is finalized we know that:
-    // - All checkpoints prior to trusted_cp are finalized
-    // - The candidate is justified (since to finalize requires a link forward to the tip of a chain of justified CPs which must include the direct successor)
-    // so all we really need to look at is finalizing the candidate or (in the case of delayed finality) one of its successors.
-    // we will ignore the delayed finality case for now
-
-    // by definition the trusted and candidate checkpoints are justified
-    let mut hightest_justified_epoch = candidate_cp.epoch;
-    trace!("Highest justified epoch: {}", hightest_justified_epoch);
-    for epoch in candidate_cp.epoch + 1.. {
-        // if we can justify the checkpoint at that epoch then do it or else abort
-        if sm_links
-            .iter()
-            .any(|link| link.source.epoch <= hightest_justified_epoch && link.target.epoch == epoch)
-        {
-            hightest_justified_epoch = epoch;
-            trace!("Successfully justified epoch: {}", epoch);
+    let sm_links = get_supermajority_links(&attested_balance_by_link, &required_balance_by_target);
+
+    // Because by definition the trusted CP is finalized we know that it is also justified, as are the checkpoints
+    // its state records as justified. Starting from these we can justify any checkpoint which is the target of a
+    // supermajority link with a justified source.
+    // The candidate is then finalized if it is justified, the checkpoints in the k epochs following it are all justified
+    // and there is a supermajority link from the candidate to the last of these (Gasper k-finality).
+    let justified = get_justified_checkpoints(trusted_cp, &trusted_justified, &sm_links);
+    trace!("Justified checkpoints: {:?}", justified);
+
+    if justified.get(&candidate_cp.epoch) != Some(&candidate_cp) {
+        warn!("Candidate is not justified by the given attestations");
+        return Ok(VerificationOutcome::JustificationGap {
+            epoch: candidate_cp.epoch,
+        });
+    }
+
+    let successors = k_finality_links(candidate_cp, &justified);
+
+    if successors.is_empty() {
+        // no way to finalize if we have a gap in the sequence of justified checkpoints
+        warn!("Non-contiguous sequence of justified checkpoints prohibits finalizing candidate");
+        return Ok(VerificationOutcome::JustificationGap {
+            epoch: candidate_cp.epoch + 1,
+        });
+    }
+
+    if let Some(link) = successors.iter().find(|link| sm_links.contains(link)) {
+        log::info!(
+            "Successfully finalized candidate by {}-finality",
+            link.target.epoch - candidate_cp.epoch
+        );
+        return Ok(VerificationOutcome::Finalized { by_link: *link });
+    }
+
+    // report the best attempt at finalizing the candidate
+    let required_balance = |link: &CasperLink| {
+        required_balance_by_target
+            .get(&link.target.epoch)
+            .copied()
+            .unwrap_or(u64::MAX)
+    };
+    let (link, attested) = successors
+        .iter()
+        .map(|link| {
+            (
+                *link,
+                attested_balance_by_link.get(link).copied().unwrap_or(0),
+            )
+        })
+        .max_by_key(|(_, attested)| *attested)
+        .expect("successors is not empty");
+    warn!(
+        "No supermajority link from the candidate finalizes it. Best link {:?} has {}/{}",
+        link,
+        attested,
+        required_balance(&link)
+    );
+    Ok(VerificationOutcome::InsufficientParticipation {
+        link,
+        attested,
+        required: required_balance(&link),
+    })
+}
+
+/// Check the trusted state could be the state of the trusted checkpoint block. The proof only shows the state root is
+/// in the header of the checkpoint block, this catches a relayer pairing the checkpoint with a state from the wrong slot.
+///
+/// The checkpoint block is the last block at or before the first slot of the trusted epoch so the state's slot is at
+/// most that slot. More than an epoch of missed slots before it is treated as inconsistent. The state then last processed
+/// justification at the end of an earlier epoch so cannot have justified the trusted epoch or anything after it
+pub fn check_trusted_state<S: Spec, SR: StateReader>(
+    state_reader: &SR,
+    trusted_cp: Checkpoint,
+) -> Result<Result<(), TrustedStateInconsistency>, StateReadError> {
+    let slot = state_reader.get_slot()?;
+    let epoch_start = S::start_slot(trusted_cp.epoch as usize) as u64;
+    if slot > epoch_start || epoch_start - slot >= S::slots_per_epoch() as u64 {
+        return Ok(Err(TrustedStateInconsistency::Slot { slot }));
+    }
+    // at genesis the finalized and justified checkpoints are the genesis epoch itself
+    let finalized = state_reader.get_finalized_epoch()?;
+    if finalized > 0 && finalized >= trusted_cp.epoch {
+        return Ok(Err(TrustedStateInconsistency::FinalizedNotBeforeTrusted {
+            epoch: finalized,
+        }));
+    }
+    let justified = state_reader.get_current_justified_checkpoint()?.epoch;
+    if justified > 0 && justified >= trusted_cp.epoch {
+        return Ok(Err(TrustedStateInconsistency::JustifiedNotBeforeTrusted {
+            epoch: justified,
+        }));
+    }
+    Ok(Ok(()))
+}
+
+/// The links that can finalize the candidate, in order of increasing k. These go from the candidate to each checkpoint
+/// in the contiguous sequence of justified checkpoints following it.
+///
+/// Like the beacon chain, which only keeps JUSTIFICATION_BITS_LENGTH epochs of justification, this stops at
+/// k = JUSTIFICATION_BITS_LENGTH - 1. A candidate that could only be finalized by a longer link is not finalized
+pub fn k_finality_links(
+    candidate_cp: Checkpoint,
+    justified: &Map<u64, Checkpoint>,
+) -> Vec<CasperLink> {
+    (1..JUSTIFICATION_BITS_LENGTH)
+        .map_while(|k| justified.get(&candidate_cp.epoch.checked_add(k)?))
+        .map(|target| CasperLink {
+            source: candidate_cp,
+            target: *target,
+        })
+        .collect()
+}
+
+// The checkpoints before the trusted checkpoint that are justified according to the trusted state. The trusted
+// checkpoint is finalized so these were justified on the same chain
+fn trusted_justified_checkpoints<S: Spec, SR: StateReader>(
+    state_reader: &SR,
+    trusted_cp: Checkpoint,
+) -> Result<Vec<Checkpoint>, Error> {
+    let mut checkpoints = Vec::from([
+        state_reader.get_current_justified_checkpoint()?,
+        state_reader.get_previous_justified_checkpoint()?,
+    ]);
+    // justification was last processed at the end of the epoch before the one containing the state's slot
+    let state_epoch = S::epoch(state_reader.get_slot()? as usize) as u64;
+    let bits = state_reader.get_justification_bits()?;
+    for i in 0..JUSTIFICATION_BITS_LENGTH {
+        let Some(epoch) = state_epoch.checked_sub(i + 1) else {
+            break;
+        };
+        if bits & (1 << i) != 0 {
+            let root = state_reader.get_block_root::<S>(S::start_slot(epoch as usize) as u64)?;
+            checkpoints.push(Checkpoint { epoch, root });
+        }
+    }
+    checkpoints.retain(|cp| cp.epoch < trusted_cp.epoch);
+    Ok(checkpoints)
+}
+
+// Apply the supermajority links in order of their target epoch to find all the checkpoints that can be justified
+// starting from the trusted checkpoint and those justified in its state. A link only justifies its target if its
+// source has already been justified.
+fn get_justified_checkpoints(
+    trusted_cp: Checkpoint,
+    trusted_justified: &[Checkpoint],
+    sm_links: &Set<CasperLink>,
+) -> Map<u64, Checkpoint> {
+    let mut justified = Map::from([(trusted_cp.epoch, trusted_cp)]);
+    // the checkpoints recorded in the state come first. At genesis these have a zero root which attestations
+    // use as their source in place of the genesis block root
+    for cp in trusted_justified {
+        justified.entry(cp.epoch).or_insert(*cp);
+    }
+
+    let mut links = sm_links
+        .iter()
+        .filter(|link| link.target.epoch > trusted_cp.epoch)
+        .collect::<Vec<_>>();
+    links.sort_by_key(|link| link.target.epoch);
+
+    for link in links {
+        if justified.get(&link.source.epoch) == Some(&link.source) {
+            let justified_target = justified.entry(link.target.epoch).or_insert(link.target);
+            if *justified_target != link.target {
+                warn!(
+                    "Conflicting checkpoints justified for epoch {}",
+                    link.target.epoch
+                );
+            }
+        }
+    }
+    justified
+}
+
+// add the balance of the attesters to the link, skipping any already counted for its target epoch
+fn add_attested_balance(
+    attested_balance_by_link: &mut Map<CasperLink, u64>,
+    attesters_by_target: &mut Map<u64, Set<usize>>,
+    link: CasperLink,
+    attester_balances: &[(usize, u64)],
+) {
+    let attesters = attesters_by_target.entry(link.target.epoch).or_default();
+    let val = attested_balance_by_link.entry(link).or_insert(0);
+    for (validator_index, balance) in attester_balances {
+        if attesters.insert(*validator_index) {
+            *val = val.saturating_add(*balance);
         } else {
-            // no way to finalize if we have a gap in the sequence of justified checkpoints
-            warn!(
-                "Non-contiguous sequence of finalized checkpoints prohibits finalizing candidate"
+            trace!(
+                "Validator {} already counted for target epoch {}",
+                validator_index,
+                link.target.epoch
             );
-            return Ok(false);
-        }
-        // see if we can now finalize the candidate by linking to the end of a sequence of justified checkpoints
-        if sm_links.iter().any(|link| {
-            link.source == candidate_cp && link.target.epoch <= hightest_justified_epoch
-        }) {
-            log::info!("Successfully finalized candidate");
-            return Ok(true);
         }
     }
+}
 
-    log::error!("Unsuccessfully finalized candidate. This should never happen.");
-    // unable to finalize candidate with given inputs
-    // this should actually be unreachable
-    Ok(false)
+// the attesting weight of each participant. Slashed validators still sign attestations but like
+// get_unslashed_attesting_indices in the spec they add no weight
+fn attester_balances<SR: StateReader>(
+    state_reader: &SR,
+    participants: &[usize],
+) -> Result<Vec<(usize, u64)>, StateReadError> {
+    participants
+        .iter()
+        .map(|validator_index| {
+            let balance = if state_reader.is_validator_slashed(*validator_index)? {
+                0
+            } else {
+                state_reader.get_validator_effective_balance(*validator_index)?
+            };
+            Ok((*validator_index, balance))
+        })
+        .collect()
 }
 
 // process attestations to produce supermajority links. A supermajority link is defined as a
-// (source, target) pair with:
-// - valid signatures by enough validators to comprise 2/3 of the total active balance in the validator set
-// - a justified source
-fn get_supermajority_links<SR: StateReader, const MAX_COMMITTEE_SIZE: usize>(
-    state_reader: &SR,
-    epoch: u64,
+// (source, target) pair with valid signatures by enough validators to comprise 2/3 of the total
+// active balance in the validator set. Whether the source is justified is checked when finalizing.
+// links with at least the required balance of their target epoch. Links to epochs without attestations processed
+// have no required balance and are never supermajority links
+fn get_supermajority_links(
     links: &Map<CasperLink, u64>,
-) -> Result<Set<CasperLink>, Error> {
-    let total_active_balance = state_reader.get_total_active_balance(epoch)?;
-    let sm_links = links
+    required_balance_by_target: &Map<u64, u64>,
+) -> Set<CasperLink> {
+    links
         .iter()
-        .filter(|(_, attesting_balance)| *attesting_balance * 3 >= &total_active_balance * 2) // check enough participation
+        .filter(|(link, attesting_balance)| {
+            required_balance_by_target
+                .get(&link.target.epoch)
+                .is_some_and(|required| **attesting_balance >= *required) // check enough participation
+        })
         .map(|(link, _)| *link)
-        .collect();
-    Ok(sm_links)
+        .collect()
+}
+
+// smallest attesting balance that makes up 2/3 of the total active balance
+fn supermajority_balance(total_active_balance: u64) -> u64 {
+    ((total_active_balance as u128 * 2 + 2) / 3) as u64
 }
 
 // this can compute validators for up to
@@ -254,17 +670,29 @@ pub fn get_shufflings_for_epoch<S: Spec, SR: StateReader>(
 pub fn get_attesting_indices<const MAX_COMMITTEE_SIZE: usize>(
     committee: &[usize],
     attestation: &Attestation<MAX_COMMITTEE_SIZE>,
-) -> Vec<usize> {
-    committee
-        .iter()
-        .enumerate()
-        .filter(|(i, _)| attestation.aggregation_bits[*i])
-        .map(|(_, validator_index)| *validator_index)
-        .collect()
+) -> Result<Vec<usize>, AttestationRejection> {
+    get_attesting_indices_from_bits(committee, &attestation.aggregation_bits)
+}
+
+/// Checks an attestation is well formed for the epoch of the committee cache and returns its attesting indices.
+/// Malformed attestations are rejected in the inner result, the outer error is for failures of the cache itself
+pub fn validate_attestation<S: Spec, const MAX_COMMITTEE_SIZE: usize>(
+    committee_cache: &CommitteeCache,
+    data: &AttestationData,
+    aggregation_bits: &Bitlist<MAX_COMMITTEE_SIZE>,
+) -> Result<Result<Vec<usize>, AttestationRejection>, Error> {
+    if let Err(reason) = data.validate::<S>(committee_cache.committees_per_slot()) {
+        return Ok(Err(reason));
+    }
+    let committee =
+        committee_cache.get_beacon_committee::<S>(data.slot as usize, data.index as usize)?;
+    Ok(get_attesting_indices_from_bits(committee, aggregation_bits))
 }
 
 fn contiguous_patches(patches: &[StatePatch]) -> bool {
-    patches.windows(2).all(|w| w[0].epoch + 1 == w[1].epoch)
+    patches
+        .windows(2)
+        .all(|w| w[0].epoch.checked_add(1) == Some(w[1].epoch))
 }
 
 #[allow(dead_code)] // TODO: Remove this when cleaning up
@@ -280,10 +708,80 @@ fn node_from_hash(h: &H256) -> Node {
     Node::try_from(h.as_ref()).expect("is right size")
 }
 
+/// The verdict reached by `verify` for inputs that satisfy all the preconditions
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationOutcome {
+    /// The candidate checkpoint is finalized by this supermajority link
+    Finalized { by_link: CasperLink },
+    /// The patch producing the state for this epoch is invalid
+    PatchRejected { epoch: u64, reason: PatchRejection },
+    /// The patches could have inserted or removed more of the active balance than the configured limit allows
+    PatchInfluenceExceeded {
+        influence: PatchInfluence,
+        max_basis_points: u64,
+    },
+    /// The checkpoint at this epoch could not be justified which prevents finalizing the candidate
+    JustificationGap { epoch: u64 },
+    /// The best link to finalize the candidate was not attested by a supermajority
+    InsufficientParticipation {
+        link: CasperLink,
+        attested: u64,
+        required: u64,
+    },
+}
+
+impl VerificationOutcome {
+    pub fn is_finalized(&self) -> bool {
+        matches!(self, Self::Finalized { .. })
+    }
+}
+
+/// Inputs which can never be used to finalize a candidate
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreconditionError {
+    CandidateNotAfterTrusted,
+    NoPatches,
+    FirstPatchEpoch {
+        expected: u64,
+        found: u64,
+    },
+    PatchesEndBeforeCandidate,
+    NonContiguousPatches,
+    EpochOverflow,
+    InvalidStateProof,
+    InvalidAncestry(AncestryRejection),
+    InvalidActiveSetHint(ActiveSetHintRejection),
+    InconsistentTrustedState(TrustedStateInconsistency),
+    /// Attestations would be verified in a fork the verifier does not implement, starting at the given epoch
+    UnsupportedFork {
+        fork_epoch: u64,
+    },
+}
+
+/// Ways the trusted state can disagree with the trusted checkpoint. See `check_trusted_state`
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrustedStateInconsistency {
+    /// The state is not from a slot the checkpoint block could have
+    Slot { slot: u64 },
+    /// The state's finalized checkpoint is not before the trusted epoch
+    FinalizedNotBeforeTrusted { epoch: u64 },
+    /// The state's current justified checkpoint is not before the trusted epoch
+    JustifiedNotBeforeTrusted { epoch: u64 },
+}
+
 #[derive(Debug)]
 pub enum Error {
+    Precondition(PreconditionError),
     StateRead(StateReadError),
     CommitteeCache,
+    SuperAttestation(SuperAttestationError),
+    Signing(SigningError),
+}
+
+impl From<PreconditionError> for Error {
+    fn from(value: PreconditionError) -> Self {
+        Self::Precondition(value)
+    }
 }
 
 impl From<StateReadError> for Error {
@@ -297,3 +795,21 @@ impl From<validator_shuffling::Error> for Error {
         Self::CommitteeCache
     }
 }
+
+impl From<SuperAttestationError> for Error {
+    fn from(value: SuperAttestationError) -> Self {
+        Self::SuperAttestation(value)
+    }
+}
+
+impl From<SigningError> for Error {
+    fn from(value: SigningError) -> Self {
+        Self::Signing(value)
+    }
+}
+
+impl From<BlsError> for Error {
+    fn from(value: BlsError) -> Self {
+        Self::Signing(value.into())
+    }
+}
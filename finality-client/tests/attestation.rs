@@ -10,9 +10,11 @@ use ethereum_consensus::signing::compute_signing_root as compute_signing_root_ra
 use ssz_rs::prelude::*;
 use std::collections::HashSet;
 use zipline_finality_client::{
+    attestation::AttestationRejection,
     get_attesting_indices, get_shufflings_for_epoch,
     signing::{compute_signing_root, verify_signed_attestation},
     state_reader::StateReader,
+    validate_attestation,
 };
 use zipline_spec::Spec;
 use zipline_spec::SpecTestSpec as S;
@@ -66,7 +68,8 @@ fn same_participants(
         .get_beacon_committee::<S>(a.data.slot as usize, a.data.index)
         .unwrap();
 
-    let participants = get_attesting_indices(committee, &to_zipline_attestation(a.clone()));
+    let participants =
+        get_attesting_indices(committee, &to_zipline_attestation(a.clone())).unwrap();
 
     let ralex_participants =
         spec::get_attesting_indices(state, &a.data, &a.aggregation_bits, context)?;
@@ -87,7 +90,7 @@ fn same_signing_domain(
     context: &ethereum_consensus::state_transition::Context,
 ) -> Result<[u8; 32], ethereum_consensus::state_transition::Error> {
     // ensure domain is the same as derived from ralexstokes.rs
-    let domain = S::beacon_attester_signing_domain();
+    let domain = S::beacon_attester_signing_domain(a.data.target.epoch);
     println!("{:?}", state.genesis_validators_root);
     let ralex_domain = spec::get_domain(
         state,
@@ -193,3 +196,61 @@ fn test_success_previous_epoch() {
         verify_attestation_signature(state, attestation, context)
     });
 }
+
+// the reason a malformed attestation from the spec tests is rejected
+fn rejection(test_case_path: &str) -> AttestationRejection {
+    let mut test_case =
+        AttestationTestCase::<spec::BeaconState, spec::Attestation>::from(test_case_path);
+    let mut rejection = None;
+    test_case.execute(|state, attestation, _context| {
+        let state_reader = DirectStateReader::new(state.clone());
+        let a = to_zipline_attestation(attestation.clone());
+        let committee_cache =
+            get_shufflings_for_epoch::<S, _>(&state_reader, a.data.target.epoch).unwrap();
+        rejection = validate_attestation::<S, { spec::MAX_VALIDATORS_PER_COMMITTEE }>(
+            &committee_cache,
+            &a.data,
+            &a.aggregation_bits,
+        )
+        .unwrap()
+        .err();
+        Ok(())
+    });
+    rejection.expect("attestation should be rejected")
+}
+
+#[test]
+fn test_rejects_mismatched_target_and_slot() {
+    assert!(matches!(
+        rejection(test_path!("invalid_mismatched_target_and_slot")),
+        AttestationRejection::TargetEpoch { .. }
+    ));
+}
+
+#[test]
+fn test_rejects_committee_index_out_of_range() {
+    assert!(matches!(
+        rejection(test_path!("invalid_index")),
+        AttestationRejection::CommitteeIndex { .. }
+    ));
+}
+
+#[test]
+fn test_rejects_wrong_aggregation_bits_length() {
+    assert!(matches!(
+        rejection(test_path!("invalid_too_many_aggregation_bits")),
+        AttestationRejection::AggregationBitsLength { .. }
+    ));
+    assert!(matches!(
+        rejection(test_path!("invalid_too_few_aggregation_bits")),
+        AttestationRejection::AggregationBitsLength { .. }
+    ));
+}
+
+#[test]
+fn test_rejects_empty_participants() {
+    assert_eq!(
+        rejection(test_path!("invalid_empty_participants_zeroes_sig")),
+        AttestationRejection::NoParticipants
+    );
+}
@@ -3,6 +3,7 @@ use ethereum_consensus::bellatrix::get_active_validator_indices;
 use ethereum_consensus::bellatrix::mainnet::BeaconState;
 use ssz_rs::prelude::*;
 use validator_shuffling::get_randao_index;
+use zipline_finality_client::attestation::Checkpoint;
 use zipline_finality_client::state_patch::StatePatch;
 use zipline_finality_client::state_reader::{PatchedStateReader, StateReadError, StateReader};
 use zipline_spec::Spec;
@@ -99,4 +100,57 @@ impl StateReader for DirectStateReader {
         let validator = self.state.validators[validator_index].clone();
         Ok((validator.activation_epoch, validator.exit_epoch))
     }
+
+    fn get_validator_activation_eligibility_epoch(
+        &self,
+        validator_index: usize,
+    ) -> Result<u64, StateReadError> {
+        Ok(self.state.validators[validator_index].activation_eligibility_epoch)
+    }
+
+    fn is_validator_slashed(&self, validator_index: usize) -> Result<bool, StateReadError> {
+        Ok(self.state.validators[validator_index].slashed)
+    }
+
+    fn get_finalized_epoch(&self) -> Result<u64, StateReadError> {
+        Ok(self.state.finalized_checkpoint.epoch)
+    }
+
+    fn get_slot(&self) -> Result<u64, StateReadError> {
+        Ok(self.state.slot)
+    }
+
+    fn get_justification_bits(&self) -> Result<u8, StateReadError> {
+        Ok(self
+            .state
+            .justification_bits
+            .iter()
+            .enumerate()
+            .fold(0, |bits, (i, bit)| bits | (u8::from(*bit) << i)))
+    }
+
+    fn get_previous_justified_checkpoint(&self) -> Result<Checkpoint, StateReadError> {
+        let cp = &self.state.previous_justified_checkpoint;
+        Ok(Checkpoint {
+            epoch: cp.epoch,
+            root: cp.root.as_ref().try_into().unwrap(),
+        })
+    }
+
+    fn get_current_justified_checkpoint(&self) -> Result<Checkpoint, StateReadError> {
+        let cp = &self.state.current_justified_checkpoint;
+        Ok(Checkpoint {
+            epoch: cp.epoch,
+            root: cp.root.as_ref().try_into().unwrap(),
+        })
+    }
+
+    fn get_block_root<S: Spec>(&self, slot: u64) -> Result<crypto::hash::H256, StateReadError> {
+        if slot >= self.state.slot || self.state.slot - slot > S::slots_per_historical_root() as u64
+        {
+            return Err(StateReadError::BlockRootUnavailable(slot));
+        }
+        let index = slot as usize % S::slots_per_historical_root();
+        Ok(self.state.block_roots[index].as_ref().try_into().unwrap())
+    }
 }
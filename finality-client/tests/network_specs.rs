@@ -0,0 +1,292 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap as Map;
+use std::process::Command;
+use validator_shuffling::{committee_shuffle_seed_from_randao, CommitteeCache, ShuffleData};
+use zipline_spec::{ChiadoSpec, GnosisSpec, HoleskySpec, SepoliaSpec, Spec};
+
+const FAR_FUTURE_EPOCH: u64 = u64::MAX;
+
+/// Data recorded from a beacon node of a network. Stored in tests/test_files/<network>/network.json
+#[derive(Serialize, Deserialize)]
+struct RecordedNetwork {
+    genesis_validators_root: String,
+    // the fork schedule as (epoch, current_version)
+    fork_schedule: Vec<(u64, String)>,
+    // epoch of the finalized state the remaining fields were read from
+    epoch: u64,
+    validator_count: usize,
+    // ranges [start, end) of the validator indices active at the epoch
+    active_validators: Vec<(usize, usize)>,
+    // the randao mix the seed of the epoch is computed from
+    randao_mix: String,
+    // (slot, index, validators) of every committee of the epoch
+    committees: Vec<(usize, usize, Vec<usize>)>,
+}
+
+//////////////////////////////////////////////
+///          Test caching.
+/// Recording the network data requires a beacon node
+/// of each network so we cache it to disk.
+/// use `<NETWORK>_BEACON_API=<node url> cargo test -p zipline-finality-client --test network_specs -- --ignored`
+/// to record the networks whose node url is set
+/////////////////////////////////////////////
+
+// The beacon API wraps every response in a data field and encodes numbers as strings
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct ApiGenesis {
+    genesis_validators_root: String,
+}
+
+#[derive(Deserialize)]
+struct ApiFork {
+    current_version: String,
+    epoch: String,
+}
+
+#[derive(Deserialize)]
+struct ApiHeader {
+    header: ApiSignedHeader,
+}
+
+#[derive(Deserialize)]
+struct ApiSignedHeader {
+    message: ApiHeaderMessage,
+}
+
+#[derive(Deserialize)]
+struct ApiHeaderMessage {
+    slot: String,
+    state_root: String,
+}
+
+#[derive(Deserialize)]
+struct ApiValidator {
+    validator: ApiValidatorRecord,
+}
+
+#[derive(Deserialize)]
+struct ApiValidatorRecord {
+    activation_epoch: String,
+    exit_epoch: String,
+}
+
+#[derive(Deserialize)]
+struct ApiRandao {
+    randao: String,
+}
+
+#[derive(Deserialize)]
+struct ApiCommittee {
+    index: String,
+    slot: String,
+    validators: Vec<String>,
+}
+
+fn fetch<T: DeserializeOwned>(api: &str, path: &str) -> T {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail"])
+        .args(["--header", "Accept: application/json"])
+        .arg(format!("{api}{path}"))
+        .output()
+        .expect("curl is required to record the network data");
+    assert!(
+        output.status.success(),
+        "{api}{path}: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice::<ApiResponse<T>>(&output.stdout)
+        .unwrap()
+        .data
+}
+
+fn parse(number: &str) -> u64 {
+    number.parse().unwrap()
+}
+
+/// Record genesis, the fork schedule and the committees of the latest finalized epoch from the beacon node at
+/// $<NETWORK>_BEACON_API
+fn record_network<S: Spec>(network: &str) {
+    let variable = format!("{}_BEACON_API", network.to_uppercase());
+    let Ok(api) = std::env::var(&variable) else {
+        println!("{variable} not set, not recording {network}");
+        return;
+    };
+    let api = api.trim_end_matches('/');
+
+    let genesis: ApiGenesis = fetch(api, "/eth/v1/beacon/genesis");
+    let fork_schedule: Vec<ApiFork> = fetch(api, "/eth/v1/config/fork_schedule");
+
+    // read everything else from one state so a new finalized checkpoint can't change it between requests
+    let header: ApiHeader = fetch(api, "/eth/v1/beacon/headers/finalized");
+    let state_id = header.header.message.state_root;
+    let epoch = parse(&header.header.message.slot) / S::slots_per_epoch() as u64;
+
+    let validators: Vec<ApiValidator> =
+        fetch(api, &format!("/eth/v1/beacon/states/{state_id}/validators"));
+    let mut active_validators: Vec<(usize, usize)> = Vec::new();
+    for (index, validator) in validators.iter().enumerate() {
+        let activation = parse(&validator.validator.activation_epoch);
+        let exit = parse(&validator.validator.exit_epoch);
+        if activation <= epoch && epoch < exit {
+            match active_validators.last_mut() {
+                Some((_, end)) if *end == index => *end += 1,
+                _ => active_validators.push((index, index + 1)),
+            }
+        }
+    }
+
+    // get_seed uses the mix of the epoch MIN_SEED_LOOKAHEAD + 1 before
+    let mix_epoch = epoch - S::min_seed_lookahead() as u64 - 1;
+    let randao: ApiRandao = fetch(
+        api,
+        &format!("/eth/v1/beacon/states/{state_id}/randao?epoch={mix_epoch}"),
+    );
+
+    let committees: Vec<ApiCommittee> = fetch(
+        api,
+        &format!("/eth/v1/beacon/states/{state_id}/committees?epoch={epoch}"),
+    );
+
+    let recorded = RecordedNetwork {
+        genesis_validators_root: genesis.genesis_validators_root,
+        fork_schedule: fork_schedule
+            .into_iter()
+            .map(|fork| (parse(&fork.epoch), fork.current_version))
+            .collect(),
+        epoch,
+        validator_count: validators.len(),
+        active_validators,
+        randao_mix: randao.randao,
+        committees: committees
+            .into_iter()
+            .map(|committee| {
+                (
+                    parse(&committee.slot) as usize,
+                    parse(&committee.index) as usize,
+                    committee
+                        .validators
+                        .iter()
+                        .map(|v| parse(v) as usize)
+                        .collect(),
+                )
+            })
+            .collect(),
+    };
+
+    let dir = format!("./tests/test_files/{network}");
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = std::fs::File::create(format!("{dir}/network.json")).unwrap();
+    serde_json::to_writer(file, &recorded).unwrap();
+}
+
+#[test]
+#[ignore]
+fn cache_gnosis_network() {
+    record_network::<GnosisSpec>("gnosis");
+}
+
+#[test]
+#[ignore]
+fn cache_chiado_network() {
+    record_network::<ChiadoSpec>("chiado");
+}
+
+#[test]
+#[ignore]
+fn cache_holesky_network() {
+    record_network::<HoleskySpec>("holesky");
+}
+
+#[test]
+#[ignore]
+fn cache_sepolia_network() {
+    record_network::<SepoliaSpec>("sepolia");
+}
+
+fn from_hex(value: &str) -> Vec<u8> {
+    hex::decode(value.trim_start_matches("0x")).unwrap()
+}
+
+/// Check a spec against the data recorded from a node of its network. The genesis validators root and fork schedule
+/// must match the network's and the committees computed with the spec's presets and signing domain must be the
+/// ones the node computed
+fn same_as_recorded_network<S: Spec>(network: &str) {
+    let path = format!("./tests/test_files/{network}/network.json");
+    let file = std::fs::File::open(&path)
+        .unwrap_or_else(|e| panic!("{path}: {e}. Record it with cache_{network}_network"));
+    let recorded: RecordedNetwork = serde_json::from_reader(file).unwrap();
+
+    assert_eq!(
+        S::genesis_validators_root().as_ref(),
+        from_hex(&recorded.genesis_validators_root),
+        "{network} genesis validators root"
+    );
+
+    // forks activated at the same epoch are listed in order so the last one applies from that epoch
+    let recorded_forks: Map<u64, Vec<u8>> = recorded
+        .fork_schedule
+        .iter()
+        .filter(|(epoch, _)| *epoch != FAR_FUTURE_EPOCH)
+        .map(|(epoch, version)| (*epoch, from_hex(version)))
+        .collect();
+    let forks: Map<u64, Vec<u8>> = S::fork_schedule()
+        .iter()
+        .map(|fork| (fork.epoch, S::fork_version(fork.epoch).to_vec()))
+        .collect();
+    assert_eq!(forks, recorded_forks, "{network} fork schedule");
+
+    let active_validator_indices: Vec<usize> = recorded
+        .active_validators
+        .iter()
+        .flat_map(|(start, end)| *start..*end)
+        .collect();
+    let randao_mix: [u8; 32] = from_hex(&recorded.randao_mix).try_into().unwrap();
+    let seed = committee_shuffle_seed_from_randao::<S>(randao_mix, recorded.epoch as usize);
+    let committee_cache = CommitteeCache::initialized::<S>(
+        ShuffleData {
+            seed,
+            active_validator_indices,
+            len_total_validators: recorded.validator_count,
+        },
+        recorded.epoch as usize,
+    )
+    .unwrap();
+
+    assert_eq!(
+        committee_cache.epoch_committee_count(),
+        recorded.committees.len(),
+        "{network} committee count"
+    );
+    for (slot, index, validators) in recorded.committees {
+        let committee = committee_cache
+            .get_beacon_committee::<S>(slot, index)
+            .unwrap();
+        assert_eq!(committee, validators, "{network} slot {slot} index {index}");
+    }
+}
+
+#[test]
+fn test_gnosis_network() {
+    same_as_recorded_network::<GnosisSpec>("gnosis");
+}
+
+#[test]
+fn test_chiado_network() {
+    same_as_recorded_network::<ChiadoSpec>("chiado");
+}
+
+#[test]
+fn test_holesky_network() {
+    same_as_recorded_network::<HoleskySpec>("holesky");
+}
+
+#[test]
+fn test_sepolia_network() {
+    same_as_recorded_network::<SepoliaSpec>("sepolia");
+}
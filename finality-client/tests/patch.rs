@@ -80,6 +80,20 @@ fn patched_matches_post<S: Spec>(
                     .1
             );
         }
+        if i < pre.validators.len() {
+            assert_eq!(
+                patched_state_reader.is_validator_slashed(i).unwrap(),
+                validator.slashed
+            );
+        } else {
+            // validators created by deposits are read from the patch records
+            assert_eq!(
+                patched_state_reader
+                    .get_validator_activation_eligibility_epoch(i)
+                    .unwrap(),
+                validator.activation_eligibility_epoch
+            );
+        }
     }
 
     assert_eq!(
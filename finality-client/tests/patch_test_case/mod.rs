@@ -1,8 +1,9 @@
 use ethereum_consensus::bellatrix::mainnet as spec;
 use ethereum_consensus::state_transition::{Context, Result};
+use ssz_rs::Deserialize;
 use test_utils::{load_snappy_ssz, Config};
 use validator_shuffling::get_randao_index;
-use zipline_finality_client::state_patch::StatePatch;
+use zipline_finality_client::state_patch::{StatePatch, ValidatorRecord};
 use zipline_spec::Spec;
 
 pub struct PatchTestCase {
@@ -81,11 +82,35 @@ pub fn patch_from_states<S: Spec>(
         },
     );
 
+    // validators newly slashed since the before state
+    let slashings: Vec<u32> = after
+        .validators
+        .iter()
+        .enumerate()
+        .filter(|(i, validator)| {
+            validator.slashed && before.validators.get(*i).is_some_and(|v| !v.slashed)
+        })
+        .map(|(i, _)| i as u32)
+        .collect();
+
+    // records for the validators created by deposits. The record has the same encoding as a Validator
+    let new_validators: Vec<ValidatorRecord> = after
+        .validators
+        .iter()
+        .skip(before.validators.len())
+        .map(|validator| {
+            ValidatorRecord::deserialize(&ssz_rs::serialize(validator).unwrap()).unwrap()
+        })
+        .collect();
+
     StatePatch {
         epoch: after_epoch,
         randao_next: randao[get_randao_index::<S>(after_epoch + 1)], // TODO: actually retrieve the correct one for the epoch
         n_deposits_processed: (after.validators.len() - before.validators.len()) as u32,
         activations: activations.try_into().unwrap(),
         exits: exits.try_into().unwrap(),
+        effective_balance_updates: Default::default(),
+        slashings: slashings.try_into().unwrap(),
+        new_validators: new_validators.try_into().unwrap(),
     }
 }
@@ -13,6 +13,10 @@ use zipline_finality_client::{
 };
 use zipline_spec::{MainnetSpec as S, Spec};
 
+const VALIDATOR_REGISTRY_LIMIT: usize = 1 << 40;
+
+type UncompressedPubkeys = List<Vector<u8, 96>, VALIDATOR_REGISTRY_LIMIT>;
+
 mod direct_state_reader;
 
 macro_rules! test_path {
@@ -83,6 +87,40 @@ fn same_validator_activation_and_exits<T: PreimageOracle<[u8; 32]>, S: Spec>(
     Ok(())
 }
 
+fn same_justification<T: PreimageOracle<[u8; 32]>, S: Spec>(
+    direct_state_reader: &DirectStateReader,
+    ssz_state_reader: &SszStateReader<T, S>,
+) -> Result<(), StateReadError> {
+    assert_eq!(
+        direct_state_reader.get_slot()?,
+        ssz_state_reader.get_slot()?
+    );
+    assert_eq!(
+        direct_state_reader.get_justification_bits()?,
+        ssz_state_reader.get_justification_bits()?
+    );
+    assert_eq!(
+        direct_state_reader.get_previous_justified_checkpoint()?,
+        ssz_state_reader.get_previous_justified_checkpoint()?
+    );
+    assert_eq!(
+        direct_state_reader.get_current_justified_checkpoint()?,
+        ssz_state_reader.get_current_justified_checkpoint()?
+    );
+    let slot = direct_state_reader.get_slot()?;
+    for block_slot in slot.saturating_sub(S::slots_per_historical_root() as u64)..slot {
+        assert_eq!(
+            direct_state_reader.get_block_root::<S>(block_slot)?,
+            ssz_state_reader.get_block_root::<S>(block_slot)?
+        );
+    }
+    assert!(matches!(
+        ssz_state_reader.get_block_root::<S>(slot),
+        Err(StateReadError::BlockRootUnavailable(_))
+    ));
+    Ok(())
+}
+
 #[test]
 fn test_equivalency() {
     setup();
@@ -101,7 +139,7 @@ fn test_equivalency() {
 
     let hashmap_oracle = HashMapOracle::from(preim);
     let ssz_state_reader: SszStateReader<_, S> =
-        SszStateReader::new(hashmap_oracle, root.as_ref().try_into().unwrap()).unwrap();
+        SszStateReader::new(hashmap_oracle, root.as_ref().try_into().unwrap(), epoch).unwrap();
 
     // check all the state read methods produce the same result
     assert!(same_randao(&direct_state_reader, &ssz_state_reader).is_ok());
@@ -113,4 +151,178 @@ fn test_equivalency() {
     )
     .is_ok());
     assert!(same_active_validators(&direct_state_reader, &ssz_state_reader, epoch).is_ok());
+    assert!(same_justification(&direct_state_reader, &ssz_state_reader).is_ok());
+}
+
+#[test]
+fn test_uncompressed_pubkeys() {
+    setup();
+
+    let mut state: spec::BeaconState =
+        load_snappy_ssz(test_path!("add_to_activation_queue/pre.ssz_snappy"))
+            .expect("Failed to load test state");
+
+    let epoch = spec::compute_epoch_at_slot(state.slot, &Context::for_mainnet());
+
+    let mut uncompressed = UncompressedPubkeys::try_from(
+        state
+            .validators
+            .iter()
+            .map(|v| {
+                let pk = crypto::bls::PublicKey::from_bytes(v.public_key.as_ref()).unwrap();
+                Vector::try_from(pk.to_uncompressed_bytes().to_vec()).unwrap()
+            })
+            .collect::<Vec<_>>(),
+    )
+    .unwrap();
+
+    let root: [u8; 32] = state.hash_tree_root().unwrap().as_ref().try_into().unwrap();
+    let pubkeys_root: [u8; 32] = uncompressed
+        .hash_tree_root()
+        .unwrap()
+        .as_ref()
+        .try_into()
+        .unwrap();
+    let mut preim: Map<[u8; 32], [u8; 64]> = Map::from_iter(state.to_merkle_tree().unwrap());
+    preim.extend(uncompressed.to_merkle_tree().unwrap());
+
+    let decompressed: SszStateReader<_, S> =
+        SszStateReader::new(HashMapOracle::from(preim.clone()), root, epoch).unwrap();
+    let ssz_state_reader: SszStateReader<_, S> = SszStateReader::with_uncompressed_pubkeys(
+        HashMapOracle::from(preim.clone()),
+        root,
+        epoch,
+        pubkeys_root,
+    )
+    .unwrap();
+
+    let all_validators = (0..state.validators.len()).collect::<Vec<_>>();
+    let (expected, _) = decompressed
+        .aggregate_validator_keys_and_balance(&all_validators)
+        .unwrap();
+    let (keys, _) = ssz_state_reader
+        .aggregate_validator_keys_and_balance(&all_validators)
+        .unwrap();
+    assert_eq!(
+        keys.iter().map(|k| k.to_bytes()).collect::<Vec<_>>(),
+        expected.iter().map(|k| k.to_bytes()).collect::<Vec<_>>()
+    );
+
+    // a list with two keys swapped must be rejected
+    let first = uncompressed[0].clone();
+    uncompressed[0] = uncompressed[1].clone();
+    uncompressed[1] = first;
+    let pubkeys_root: [u8; 32] = uncompressed
+        .hash_tree_root()
+        .unwrap()
+        .as_ref()
+        .try_into()
+        .unwrap();
+    preim.extend(uncompressed.to_merkle_tree().unwrap());
+    assert!(matches!(
+        SszStateReader::<_, S>::with_uncompressed_pubkeys(
+            HashMapOracle::from(preim.clone()),
+            root,
+            epoch,
+            pubkeys_root
+        ),
+        Err(StateReadError::UncompressedPubkeyMismatch(0))
+    ));
+
+    // as must a list that is missing a key
+    uncompressed.pop();
+    let pubkeys_root: [u8; 32] = uncompressed
+        .hash_tree_root()
+        .unwrap()
+        .as_ref()
+        .try_into()
+        .unwrap();
+    preim.extend(uncompressed.to_merkle_tree().unwrap());
+    assert!(matches!(
+        SszStateReader::<_, S>::with_uncompressed_pubkeys(
+            HashMapOracle::from(preim),
+            root,
+            epoch,
+            pubkeys_root
+        ),
+        Err(StateReadError::UncompressedPubkeysLength)
+    ));
+}
+
+#[test]
+fn test_lazy_equivalency() {
+    setup();
+
+    let mut state: spec::BeaconState =
+        load_snappy_ssz(test_path!("add_to_activation_queue/pre.ssz_snappy"))
+            .expect("Failed to load test state");
+
+    let epoch = spec::compute_epoch_at_slot(state.slot, &Context::for_mainnet());
+
+    let direct_state_reader = DirectStateReader::new(state.clone());
+
+    let root: [u8; 32] = state.hash_tree_root().unwrap().as_ref().try_into().unwrap();
+    let preim: Map<[u8; 32], [u8; 64]> = Map::from_iter(state.to_merkle_tree().unwrap());
+
+    let ssz_state_reader: SszStateReader<_, S> =
+        SszStateReader::lazy(HashMapOracle::from(preim), root, epoch, None).unwrap();
+
+    assert!(same_validator_count(&direct_state_reader, &ssz_state_reader).is_ok());
+    assert!(same_validator_activation_and_exits(
+        &direct_state_reader,
+        &ssz_state_reader,
+        state.validators.len(),
+    )
+    .is_ok());
+    assert!(same_active_validators(&direct_state_reader, &ssz_state_reader, epoch).is_ok());
+    assert_eq!(
+        direct_state_reader.get_total_active_balance(epoch).unwrap(),
+        ssz_state_reader.get_total_active_balance(epoch).unwrap()
+    );
+
+    // the fields not needed for the active set are loaded on first use
+    for i in (0..state.validators.len()).rev() {
+        assert_eq!(
+            direct_state_reader.is_validator_slashed(i).unwrap(),
+            ssz_state_reader.is_validator_slashed(i).unwrap()
+        );
+        assert_eq!(
+            direct_state_reader
+                .get_validator_activation_eligibility_epoch(i)
+                .unwrap(),
+            ssz_state_reader
+                .get_validator_activation_eligibility_epoch(i)
+                .unwrap()
+        );
+        assert_eq!(
+            direct_state_reader
+                .get_validator_effective_balance(i)
+                .unwrap(),
+            ssz_state_reader.get_validator_effective_balance(i).unwrap()
+        );
+    }
+    assert!(matches!(
+        ssz_state_reader.is_validator_slashed(state.validators.len()),
+        Err(StateReadError::ValidatorIndexOutOfRange(_))
+    ));
+
+    // keys are loaded on first use and come from the cache after that
+    let indices = [3, 1, 3];
+    let (expected, _) = direct_state_reader
+        .aggregate_validator_keys_and_balance(&indices)
+        .unwrap();
+    for _ in 0..2 {
+        let (keys, _) = ssz_state_reader
+            .aggregate_validator_keys_and_balance(&indices)
+            .unwrap();
+        assert_eq!(
+            keys.iter().map(|k| k.to_bytes()).collect::<Vec<_>>(),
+            expected.iter().map(|k| k.to_bytes()).collect::<Vec<_>>()
+        );
+    }
+
+    assert!(matches!(
+        ssz_state_reader.aggregate_validator_keys_and_balance(&[state.validators.len()]),
+        Err(StateReadError::ValidatorIndexOutOfRange(_))
+    ));
 }
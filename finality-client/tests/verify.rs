@@ -3,15 +3,34 @@ extern crate alloc;
 use alloc::collections::btree_map::BTreeMap as Map;
 use alloc::vec::Vec;
 use cannon_unicorn::{new_cannon_unicorn, run, write_input, write_program, TraceConfig, UnsyncRam};
+use crypto::bls::Signature;
 use crypto::hash::hash;
 use ethereum_consensus::bellatrix::mainnet as spec;
 use preimage_oracle::hashmap_oracle::HashMapOracle;
+use ssz_rs::compute_proof;
 use ssz_rs::prelude::*;
 use std::io::Write;
 use std::sync::Once;
 use zipline_finality_client::ssz_state_reader::{PatchedSszStateReader, SszStateReader};
-use zipline_finality_client::{input::ZiplineInput, verify};
-use zipline_spec::{MainnetSpec, SpecTestSpec};
+use zipline_finality_client::{
+    active_set::{ActiveSetHint, ActiveSetHintRejection},
+    ancestry::{AncestryRejection, BlockRootProof, CandidateAncestry},
+    attestation::{Attestation, CasperLink, Checkpoint},
+    check_trusted_state, get_attesting_indices, get_shufflings_for_epoch,
+    input::ZiplineInput,
+    k_finality_links,
+    output::{expected_output_hash, Verdict},
+    signing::attestation_signing_root,
+    state_patch::{EffectiveBalanceUpdate, PatchRejection, StatePatch, ValidatorRecord},
+    state_reader::{PatchedStateReader, StateReader},
+    super_attestation::{
+        CompressedAttestation, CompressedAttestationData, Participation, SuperAttestation,
+        SuperAttestationError,
+    },
+    verify, verify_with_config, Error, PreconditionError, TrustedStateInconsistency,
+    VerificationOutcome, VerifyConfig, JUSTIFICATION_BITS_LENGTH,
+};
+use zipline_spec::{ChainConfig, MainnetSpec, RuntimeSpec, Spec, SpecTestSpec};
 use zipline_test_case::ZiplineTestCase;
 
 use crate::direct_state_reader::DirectStateReader;
@@ -62,6 +81,36 @@ fn cache_finality_rule_3() {
     }
 }
 
+#[test]
+#[ignore]
+fn cache_finality_rule_1() {
+    let test_cases =
+        ZiplineTestCase::from_eth_spec_path::<SpecTestSpec>(test_path!("finality_rule_1"));
+    for (i, case) in test_cases.iter().enumerate() {
+        case.serialize_to_file(&format!("test_finality_rule_1_{}.ssz", i));
+    }
+}
+
+#[test]
+#[ignore]
+fn cache_finality_rule_2() {
+    let test_cases =
+        ZiplineTestCase::from_eth_spec_path::<SpecTestSpec>(test_path!("finality_rule_2"));
+    for (i, case) in test_cases.iter().enumerate() {
+        case.serialize_to_file(&format!("test_finality_rule_2_{}.ssz", i));
+    }
+}
+
+#[test]
+#[ignore]
+fn cache_finality_rule_4() {
+    let test_cases =
+        ZiplineTestCase::from_eth_spec_path::<SpecTestSpec>(test_path!("finality_rule_4"));
+    for (i, case) in test_cases.iter().enumerate() {
+        case.serialize_to_file(&format!("test_finality_rule_4_{}.ssz", i));
+    }
+}
+
 // generates the files needed to run the high level demo script
 // these are kept in the repo to make running the demo as easy as possible
 #[test]
@@ -131,6 +180,772 @@ fn test_finality_rule_3_1() {
     run_test_native(test)
 }
 
+#[test]
+fn test_finality_rule_1_0() {
+    setup();
+    let test = ZiplineTestCase::deserialize_from_file("test_finality_rule_1_0.ssz");
+    run_test_native(test)
+}
+
+#[test]
+fn test_finality_rule_2_0() {
+    setup();
+    let test = ZiplineTestCase::deserialize_from_file("test_finality_rule_2_0.ssz");
+    run_test_native(test)
+}
+
+#[test]
+fn test_finality_rule_4_0() {
+    setup();
+    let test = ZiplineTestCase::deserialize_from_file("test_finality_rule_4_0.ssz");
+    run_test_native(test)
+}
+
+#[test]
+fn runtime_spec_matches_compile_time_spec() {
+    setup();
+    // the only test in this binary that installs a config
+    RuntimeSpec::install(ChainConfig::from_spec::<SpecTestSpec>()).unwrap();
+    assert_eq!(
+        RuntimeSpec::beacon_attester_signing_domain(0),
+        SpecTestSpec::beacon_attester_signing_domain(0)
+    );
+
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let input = test.to_input();
+    let result = verify::<
+        RuntimeSpec,
+        PatchedDirectStateReader,
+        { spec::MAX_VALIDATORS_PER_COMMITTEE },
+        1000,
+        10,
+    >(DirectStateReader::new(test.state.clone()), input.clone())
+    .unwrap();
+    assert_eq!(result, verify_native(&test, input).unwrap());
+}
+
+#[test]
+fn rejects_candidate_not_after_trusted() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    input.candidate_cp.epoch = input.trusted_cp.epoch;
+    assert!(matches!(
+        verify_native(&test, input),
+        Err(Error::Precondition(
+            PreconditionError::CandidateNotAfterTrusted
+        ))
+    ));
+}
+
+#[test]
+fn rejects_attestations_from_electra() {
+    let electra_epoch = 364032;
+    let verify_before = |candidate_epoch: u64| {
+        let mut input = ZiplineInput::<{ spec::MAX_VALIDATORS_PER_COMMITTEE }, 1000, 10>::default();
+        input.trusted_cp.epoch = candidate_epoch - 1;
+        input.candidate_cp.epoch = candidate_epoch;
+        input.patches.push(StatePatch {
+            epoch: candidate_epoch,
+            ..Default::default()
+        });
+        verify::<
+            MainnetSpec,
+            PatchedDirectStateReader,
+            { spec::MAX_VALIDATORS_PER_COMMITTEE },
+            1000,
+            10,
+        >(DirectStateReader::default(), input)
+    };
+    // attestations for the epoch after the last patch are verified so this reaches Electra
+    assert!(matches!(
+        verify_before(electra_epoch - 1),
+        Err(Error::Precondition(PreconditionError::UnsupportedFork {
+            fork_epoch: 364032
+        }))
+    ));
+    // one epoch earlier the input gets as far as checking the state proof
+    assert!(matches!(
+        verify_before(electra_epoch - 2),
+        Err(Error::Precondition(PreconditionError::InvalidStateProof))
+    ));
+}
+
+#[test]
+fn rejects_invalid_state_proof() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    input.state_proof[0] = [0xff; 32];
+    assert!(matches!(
+        verify_native(&test, input),
+        Err(Error::Precondition(PreconditionError::InvalidStateProof))
+    ));
+}
+
+#[test]
+fn rejects_trusted_state_from_wrong_slot() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    // the proof only covers the root so the trusted epoch can be moved away from the state's slot
+    input.trusted_cp.epoch += 2;
+    input.candidate_cp.epoch += 2;
+    for patch in input.patches.iter_mut() {
+        patch.epoch += 2;
+    }
+    let config = VerifyConfig {
+        check_trusted_state: true,
+        ..Default::default()
+    };
+    let result = verify_with_config::<
+        SpecTestSpec,
+        PatchedDirectStateReader,
+        { spec::MAX_VALIDATORS_PER_COMMITTEE },
+        1000,
+        10,
+    >(DirectStateReader::new(test.state.clone()), input, &config);
+    assert!(matches!(
+        result,
+        Err(Error::Precondition(
+            PreconditionError::InconsistentTrustedState(TrustedStateInconsistency::Slot { .. })
+        ))
+    ));
+}
+
+#[test]
+fn accepts_trusted_state_at_epoch_start() {
+    setup();
+    let test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let reader = DirectStateReader::new(test.state.clone());
+    // the first epoch starting at or after the state's slot is the one the state could be a checkpoint for
+    let slots_per_epoch = SpecTestSpec::slots_per_epoch() as u64;
+    let epoch = (test.state.slot + slots_per_epoch - 1) / slots_per_epoch;
+    let trusted_cp = Checkpoint {
+        epoch,
+        root: test.trusted.root,
+    };
+    assert_eq!(
+        check_trusted_state::<SpecTestSpec, _>(&reader, trusted_cp).unwrap(),
+        Ok(())
+    );
+    let too_late = Checkpoint {
+        epoch: epoch + 1,
+        ..trusted_cp
+    };
+    assert_eq!(
+        check_trusted_state::<SpecTestSpec, _>(&reader, too_late).unwrap(),
+        Err(TrustedStateInconsistency::Slot {
+            slot: test.state.slot
+        })
+    );
+}
+
+#[test]
+fn rejects_trusted_state_that_justified_or_finalized_the_trusted_epoch() {
+    let slots_per_epoch = SpecTestSpec::slots_per_epoch() as u64;
+    let trusted_cp = Checkpoint {
+        epoch: 10,
+        root: [1; 32],
+    };
+    let mut state = spec::BeaconState {
+        slot: trusted_cp.epoch * slots_per_epoch,
+        ..Default::default()
+    };
+    let check = |state: &spec::BeaconState| {
+        check_trusted_state::<SpecTestSpec, _>(&DirectStateReader::new(state.clone()), trusted_cp)
+            .unwrap()
+    };
+    state.current_justified_checkpoint.epoch = 9;
+    state.finalized_checkpoint.epoch = 8;
+    assert_eq!(check(&state), Ok(()));
+
+    state.current_justified_checkpoint.epoch = 10;
+    assert_eq!(
+        check(&state),
+        Err(TrustedStateInconsistency::JustifiedNotBeforeTrusted { epoch: 10 })
+    );
+
+    state.finalized_checkpoint.epoch = 10;
+    assert_eq!(
+        check(&state),
+        Err(TrustedStateInconsistency::FinalizedNotBeforeTrusted { epoch: 10 })
+    );
+}
+
+#[test]
+fn k_finality_stops_before_justification_bits_length() {
+    let checkpoint = |epoch: u64| Checkpoint {
+        epoch,
+        root: [epoch as u8; 32],
+    };
+    let candidate = checkpoint(10);
+    // justified for far longer than JUSTIFICATION_BITS_LENGTH epochs after the candidate
+    let justified = Map::from_iter((10..20).map(|epoch| (epoch, checkpoint(epoch))));
+    let links = k_finality_links(candidate, &justified);
+    assert_eq!(
+        links,
+        (11..10 + JUSTIFICATION_BITS_LENGTH)
+            .map(|epoch| CasperLink {
+                source: candidate,
+                target: checkpoint(epoch),
+            })
+            .collect::<Vec<_>>()
+    );
+    // the longest link is for k = 3
+    assert_eq!(links.last().unwrap().target.epoch, 13);
+
+    // a gap ends the sequence
+    let mut justified = justified;
+    justified.remove(&12);
+    assert_eq!(k_finality_links(candidate, &justified).len(), 1);
+    justified.remove(&11);
+    assert!(k_finality_links(candidate, &justified).is_empty());
+}
+
+#[test]
+fn reports_justification_gap_without_attestations() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    input.attestations = Default::default();
+    let candidate_epoch = input.candidate_cp.epoch;
+    assert_eq!(
+        verify_native(&test, input).unwrap(),
+        VerificationOutcome::JustificationGap {
+            epoch: candidate_epoch
+        }
+    );
+}
+
+#[test]
+fn skips_attestation_with_wrong_aggregation_bits_length() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let input = test.to_input();
+    let outcome = verify_native(&test, input.clone()).unwrap();
+
+    // one bit too many and one bit too few for the committee
+    let mut malformed = input.clone();
+    let mut too_long = input.attestations[0].clone();
+    too_long.aggregation_bits.push(true);
+    malformed.attestations.push(too_long);
+    let mut too_short = input.attestations[0].clone();
+    too_short.aggregation_bits.pop();
+    malformed.attestations.push(too_short);
+
+    assert_eq!(verify_native(&test, malformed).unwrap(), outcome);
+}
+
+#[test]
+fn rejects_malformed_input_bytes() {
+    type Input = ZiplineInput<{ spec::MAX_VALIDATORS_PER_COMMITTEE }, 1000, 10>;
+    assert!(Input::from_ssz_bytes(&[0xff; 7]).is_err());
+}
+
+#[test]
+fn accepts_valid_block_root_ancestry() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    input.candidate_ancestry = make_candidate_ancestry(&mut test.state, &mut input.candidate_cp);
+    let candidate_epoch = input.candidate_cp.epoch;
+    // the synthetic candidate passes the ancestry checks but is not justified by any attestations
+    assert_eq!(
+        verify_native(&test, input).unwrap(),
+        VerificationOutcome::JustificationGap {
+            epoch: candidate_epoch
+        }
+    );
+}
+
+#[test]
+fn rejects_invalid_block_root_proof() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    input.candidate_ancestry = make_candidate_ancestry(&mut test.state, &mut input.candidate_cp);
+    let block_root = input
+        .candidate_ancestry
+        .block_roots
+        .iter_mut()
+        .next()
+        .unwrap();
+    block_root.root = [0xaa; 32];
+    let slot = block_root.slot;
+    assert!(matches!(
+        verify_native(&test, input),
+        Err(Error::Precondition(PreconditionError::InvalidAncestry(
+            AncestryRejection::InvalidBlockRootProof { slot: s }
+        ))) if s == slot
+    ));
+}
+
+#[test]
+fn rejects_block_root_outside_candidate_history() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    input.candidate_ancestry = make_candidate_ancestry(&mut test.state, &mut input.candidate_cp);
+    let block_root = input
+        .candidate_ancestry
+        .block_roots
+        .iter_mut()
+        .next()
+        .unwrap();
+    block_root.slot = input.candidate_ancestry.slot;
+    assert!(matches!(
+        verify_native(&test, input),
+        Err(Error::Precondition(PreconditionError::InvalidAncestry(
+            AncestryRejection::SlotOutOfRange { .. }
+        )))
+    ));
+}
+
+#[test]
+fn accepts_valid_active_set_hint() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    input.active_set_hint = make_active_set_hint(&test.state, input.trusted_cp.epoch);
+    let expected = test.expected_result;
+    assert_eq!(
+        verify_native(&test, input.clone()).unwrap().is_finalized(),
+        expected
+    );
+    assert_eq!(
+        verify_ssz(&mut test, input).unwrap().is_finalized(),
+        expected
+    );
+}
+
+#[test]
+fn rejects_active_set_hint_with_wrong_balance() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    input.active_set_hint = make_active_set_hint(&test.state, input.trusted_cp.epoch);
+    input.active_set_hint.total_active_balance += 1;
+    assert!(matches!(
+        verify_ssz(&mut test, input),
+        Err(Error::Precondition(
+            PreconditionError::InvalidActiveSetHint(
+                ActiveSetHintRejection::TotalActiveBalance { .. }
+            )
+        ))
+    ));
+}
+
+#[test]
+fn rejects_active_set_hint_with_wrong_validator() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    input.active_set_hint = make_active_set_hint(&test.state, input.trusted_cp.epoch);
+    let active = input.active_set_hint.active_validators[0];
+    input.active_set_hint.active_validators.set(0, !active);
+    assert!(matches!(
+        verify_native(&test, input.clone()),
+        Err(Error::Precondition(
+            PreconditionError::InvalidActiveSetHint(ActiveSetHintRejection::ActivityMismatch {
+                validator_index: 0
+            })
+        ))
+    ));
+    assert!(matches!(
+        verify_ssz(&mut test, input),
+        Err(Error::Precondition(
+            PreconditionError::InvalidActiveSetHint(ActiveSetHintRejection::ActivityMismatch {
+                validator_index: 0
+            })
+        ))
+    ));
+}
+
+#[test]
+fn rejects_patches_exceeding_influence_limit() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    input.patches.iter_mut().next().unwrap().activations.push(0);
+    let config = VerifyConfig {
+        max_patch_influence_bps: Some(0),
+        ..Default::default()
+    };
+    let result = verify_with_config::<
+        SpecTestSpec,
+        PatchedDirectStateReader,
+        { spec::MAX_VALIDATORS_PER_COMMITTEE },
+        1000,
+        10,
+    >(DirectStateReader::new(test.state.clone()), input, &config);
+    assert!(matches!(
+        result,
+        Ok(VerificationOutcome::PatchInfluenceExceeded {
+            max_basis_points: 0,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn strict_patches_accept_honest_patches() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let input = test.to_input();
+    assert_eq!(
+        verify_strict(&test, input).unwrap().is_finalized(),
+        test.expected_result
+    );
+}
+
+#[test]
+fn strict_patches_reject_activation_at_wrong_epoch() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    // validator 0 is active from genesis
+    input.patches.iter_mut().next().unwrap().activations.push(0);
+    assert!(matches!(
+        verify_strict(&test, input),
+        Ok(VerificationOutcome::PatchRejected {
+            reason: PatchRejection::ActivationEpochMismatch {
+                validator_index: 0,
+                ..
+            },
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rejects_balance_decrease_of_more_than_one_increment() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    let old_balance = test.state.validators[0].effective_balance;
+    input
+        .patches
+        .iter_mut()
+        .next()
+        .unwrap()
+        .effective_balance_updates
+        .push(EffectiveBalanceUpdate {
+            validator_index: 0,
+            effective_balance: old_balance - 2 * SpecTestSpec::effective_balance_increment(),
+        });
+    assert!(matches!(
+        verify_native(&test, input),
+        Ok(VerificationOutcome::PatchRejected {
+            reason: PatchRejection::EffectiveBalanceChangeTooLarge {
+                validator_index: 0,
+                from,
+                ..
+            },
+            ..
+        }) if from == old_balance
+    ));
+}
+
+#[test]
+fn slashed_validators_add_no_attesting_weight() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    // the candidate can only be justified by attestations checked against the patched states, so with every
+    // validator slashed in the first patch there is no weight left to finalize it
+    let mut input = test.to_input();
+    let patch = input.patches.iter_mut().next().unwrap();
+    for (validator_index, validator) in test.state.validators.iter().enumerate() {
+        if !validator.slashed {
+            patch.slashings.push(validator_index as u32);
+        }
+    }
+    assert!(!verify_native(&test, input).unwrap().is_finalized());
+}
+
+#[test]
+fn rejects_patch_with_missing_new_validators() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    let patch = input.patches.iter_mut().next().unwrap();
+    patch.n_deposits_processed = 2;
+    patch.new_validators.push(ValidatorRecord {
+        activation_epoch: u64::MAX,
+        exit_epoch: u64::MAX,
+        ..Default::default()
+    });
+    assert!(matches!(
+        verify_native(&test, input),
+        Ok(VerificationOutcome::PatchRejected {
+            reason: PatchRejection::NewValidatorCount {
+                n_deposits: 2,
+                n_records: 1
+            },
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_finality_rule_3_0_super_attestation() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    input.super_attestation = to_super_attestation(&input);
+    input.attestations = Default::default();
+    assert_eq!(
+        verify_native(&test, input).unwrap().is_finalized(),
+        test.expected_result
+    );
+}
+
+#[test]
+fn ignores_attestations_with_invalid_signatures() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let (input, by_link) = input_with_half_of_finalizing_attestations(&mut test);
+    let outcome = verify_native(&test, input.clone()).unwrap();
+
+    // every attestation for the link again, now with a valid signature but not over its own data. These are
+    // batched with the valid attestations and must be picked out by bisection
+    let mut tampered = input.clone();
+    for a in test
+        .to_input()
+        .attestations
+        .iter()
+        .filter(|a| a.data.link() == by_link)
+    {
+        let mut a = a.clone();
+        a.signature = input.attestations[0].signature.clone();
+        tampered.attestations.push(a);
+    }
+    assert_eq!(verify_native(&test, tampered).unwrap(), outcome);
+}
+
+#[test]
+fn ignores_super_attestation_with_invalid_signature() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    input.super_attestation = to_super_attestation(&input);
+    // a valid signature but not over the super attestation messages
+    input.super_attestation.signature = input.attestations[0].signature.clone();
+    input.attestations = Default::default();
+    let candidate_epoch = input.candidate_cp.epoch;
+    assert_eq!(
+        verify_native(&test, input).unwrap(),
+        VerificationOutcome::JustificationGap {
+            epoch: candidate_epoch
+        }
+    );
+}
+
+// the input with only half of the attestations for the link that finalizes the candidate so it no longer finalizes
+fn input_with_half_of_finalizing_attestations(
+    test: &mut ZiplineTestCase,
+) -> (
+    ZiplineInput<{ spec::MAX_VALIDATORS_PER_COMMITTEE }, 1000, 10>,
+    CasperLink,
+) {
+    let mut input = test.to_input();
+    let VerificationOutcome::Finalized { by_link } = verify_native(test, input.clone()).unwrap() else {
+        panic!("test case should finalize");
+    };
+    let finalizing = input
+        .attestations
+        .iter()
+        .filter(|a| a.data.link() == by_link)
+        .count();
+    let mut kept = 0;
+    let attestations = input
+        .attestations
+        .iter()
+        .filter(|a| {
+            if a.data.link() != by_link {
+                return true;
+            }
+            kept += 1;
+            kept <= finalizing / 2
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    input.attestations = attestations.try_into().unwrap();
+    (input, by_link)
+}
+
+#[test]
+fn supermajority_is_two_thirds_of_the_target_epoch_balance() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let (mut input, by_link) = input_with_half_of_finalizing_attestations(&mut test);
+    // lowering a balance in the first patch makes every later epoch's total differ from the trusted epoch's
+    let old_balance = test.state.validators[0].effective_balance;
+    input.patches[0]
+        .effective_balance_updates
+        .push(EffectiveBalanceUpdate {
+            validator_index: 0,
+            effective_balance: old_balance - SpecTestSpec::effective_balance_increment(),
+        });
+    let Ok(VerificationOutcome::InsufficientParticipation { link, required, .. }) =
+        verify_native(&test, input.clone())
+    else {
+        panic!("half the attestations should not finalize");
+    };
+    assert_eq!(link, by_link);
+
+    // attestations targeting an epoch are weighed with the state patched up to the epoch before it
+    let reader = DirectStateReader::new(test.state.clone());
+    let trusted_total = reader
+        .get_total_active_balance(input.trusted_cp.epoch)
+        .unwrap();
+    let patched = input
+        .patches
+        .iter()
+        .filter(|patch| patch.epoch < link.target.epoch)
+        .fold(PatchedDirectStateReader::new(reader), |patched, patch| {
+            patched.with_patch(patch.clone())
+        });
+    let target_total = patched.get_total_active_balance(link.target.epoch).unwrap();
+    assert_eq!(
+        target_total,
+        trusted_total - SpecTestSpec::effective_balance_increment()
+    );
+    assert_eq!(required, (target_total * 2 + 2) / 3);
+}
+
+#[test]
+fn duplicated_attestations_do_not_inflate_participation() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let (input, by_link) = input_with_half_of_finalizing_attestations(&mut test);
+    let outcome = verify_native(&test, input.clone()).unwrap();
+    assert!(!outcome.is_finalized());
+
+    // submitting the remaining attestations for the link twice counts each validator once
+    let mut duplicated = input.clone();
+    for a in input
+        .attestations
+        .iter()
+        .filter(|a| a.data.link() == by_link)
+    {
+        duplicated.attestations.push(a.clone());
+    }
+    assert_eq!(verify_native(&test, duplicated).unwrap(), outcome);
+}
+
+#[test]
+fn super_attestation_overlapping_attestations_does_not_inflate_participation() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let (input, _) = input_with_half_of_finalizing_attestations(&mut test);
+    let outcome = verify_native(&test, input.clone()).unwrap();
+
+    // the super attestation covers the same committees as the individual attestations
+    let mut overlapping = input.clone();
+    overlapping.super_attestation = to_super_attestation(&input);
+    assert_eq!(verify_native(&test, overlapping).unwrap(), outcome);
+}
+
+#[test]
+fn double_votes_for_a_target_are_counted_once() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let input = test.to_input();
+    let VerificationOutcome::Finalized { by_link } = verify_native(&test, input.clone()).unwrap() else {
+        panic!("test case should finalize");
+    };
+
+    // every validator voting for the finalizing link also votes for its target from another source
+    let double_votes = input
+        .attestations
+        .iter()
+        .filter(|a| a.data.link() == by_link)
+        .map(|a| {
+            let mut a = a.clone();
+            a.data.source.root = [0xaa; 32];
+            resign_attestation(&test, &input, &mut a);
+            a
+        })
+        .collect::<Vec<_>>();
+
+    // a second vote for the target adds no balance to its link
+    let mut voted_after = input.clone();
+    for a in double_votes.iter() {
+        voted_after.attestations.push(a.clone());
+    }
+    assert_eq!(
+        verify_native(&test, voted_after).unwrap(),
+        VerificationOutcome::Finalized { by_link }
+    );
+
+    // and the first vote takes the validator's balance away from the finalizing link
+    let mut voted_before = input.clone();
+    voted_before.attestations = double_votes
+        .iter()
+        .chain(input.attestations.iter())
+        .cloned()
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap();
+    assert_ne!(
+        verify_native(&test, voted_before).unwrap(),
+        VerificationOutcome::Finalized { by_link }
+    );
+}
+
+#[test]
+fn skips_malformed_attestations() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let input = test.to_input();
+    let outcome = verify_native(&test, input.clone()).unwrap();
+
+    let mut malformed = input.clone();
+    let mut wrong_target = input.attestations[0].clone();
+    wrong_target.data.target.epoch += 1;
+    malformed.attestations.push(wrong_target);
+    let mut wrong_index = input.attestations[0].clone();
+    wrong_index.data.index = SpecTestSpec::max_committees_per_slot() as u64;
+    malformed.attestations.push(wrong_index);
+    let mut wrong_bits = input.attestations[0].clone();
+    wrong_bits.aggregation_bits.push(true);
+    malformed.attestations.push(wrong_bits);
+
+    assert_eq!(verify_native(&test, malformed).unwrap(), outcome);
+}
+
+#[test]
+fn rejects_super_attestation_with_unknown_checkpoint() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    input.super_attestation = to_super_attestation(&input);
+    let n_checkpoints = input.super_attestation.checkpoints.len() as u32;
+    input.super_attestation.attestations[0].data.target = n_checkpoints;
+    assert!(matches!(
+        verify_native(&test, input),
+        Err(Error::SuperAttestation(
+            SuperAttestationError::UnknownCheckpointId(id)
+        )) if id == n_checkpoints
+    ));
+}
+
+#[test]
+fn output_hash_commits_to_verdict_and_candidate() {
+    setup();
+    let mut test = ZiplineTestCase::deserialize_from_file("test_finality_rule_3_0.ssz");
+    let mut input = test.to_input();
+    let finalized = expected_output_hash(&input, Verdict::Finalized);
+    assert_ne!(
+        finalized,
+        expected_output_hash(&input, Verdict::NotFinalized)
+    );
+
+    input.candidate_cp.root = [0xaa; 32];
+    assert_ne!(finalized, expected_output_hash(&input, Verdict::Finalized));
+}
+
 #[test]
 fn ssz_test_finality_rule_3_0() {
     setup();
@@ -184,6 +999,7 @@ fn ssz_mainnet() {
     let reader = SszStateReader::new(
         hashmap_oracle,
         inputs_deser.state_root.as_ref().try_into().unwrap(),
+        inputs_deser.trusted_cp.epoch,
     )
     .unwrap();
 
@@ -196,7 +1012,7 @@ fn ssz_mainnet() {
     >(reader, inputs_deser)
     .unwrap();
 
-    assert!(result);
+    assert!(result.is_finalized());
 }
 
 // Ignore because it takes too long to run
@@ -231,13 +1047,14 @@ fn unicorn_mainnet() {
     // Run in the emulator!
     let (snapshot, steps, emulation_output) = run(&mut mu, 0);
 
-    let mut expected_result = [0xff_u8; 68];
-    expected_result[..4].copy_from_slice(&[0x13, 0x37, 0xf0, 0x0d]);
-    expected_result[4..].copy_from_slice(&[0x00; 64]);
     println!("snapshot: {:?}", snapshot);
     println!("steps: {:?}", steps);
     println!("emulation_output: {:?}", emulation_output);
-    assert_eq!(emulation_output, expected_result);
+    assert_eq!(emulation_output[..4], [0x13, 0x37, 0xf0, 0x0d]);
+    assert_eq!(
+        emulation_output[4..36],
+        expected_output_hash(&inputs_deser, Verdict::Finalized)
+    );
 }
 
 // Ignoring because we need a better way to handle bellatrix vs capella BeaconStates in DirectStateReader
@@ -293,16 +1110,206 @@ fn native_mainnet() {
 ///   - any node in the state SSZ merkle tree
 
 fn run_test_native(mut test: ZiplineTestCase) {
+    let input = test.to_input();
+    let result = verify_native(&test, input).unwrap();
+    assert_eq!(result.is_finalized(), test.expected_result);
+}
+
+fn verify_native(
+    test: &ZiplineTestCase,
+    input: ZiplineInput<{ spec::MAX_VALIDATORS_PER_COMMITTEE }, 1000, 10>,
+) -> Result<VerificationOutcome, Error> {
     let reader = DirectStateReader::new(test.state.clone());
-    let result = verify::<
+    verify::<SpecTestSpec, PatchedDirectStateReader, { spec::MAX_VALIDATORS_PER_COMMITTEE }, 1000, 10>(
+        reader, input,
+    )
+}
+
+fn verify_strict(
+    test: &ZiplineTestCase,
+    input: ZiplineInput<{ spec::MAX_VALIDATORS_PER_COMMITTEE }, 1000, 10>,
+) -> Result<VerificationOutcome, Error> {
+    let config = VerifyConfig {
+        strict_patches: true,
+        ..Default::default()
+    };
+    verify_with_config::<
         SpecTestSpec,
         PatchedDirectStateReader,
         { spec::MAX_VALIDATORS_PER_COMMITTEE },
         1000,
         10,
-    >(reader, test.to_input())
+    >(DirectStateReader::new(test.state.clone()), input, &config)
+}
+
+fn verify_ssz(
+    test: &mut ZiplineTestCase,
+    input: ZiplineInput<{ spec::MAX_VALIDATORS_PER_COMMITTEE }, 1000, 10>,
+) -> Result<VerificationOutcome, Error> {
+    let oracle_provider = make_test_oracle_provider(&input, &mut test.state);
+    let reader = SszStateReader::lazy(
+        HashMapOracle::from(oracle_provider),
+        input.state_root,
+        input.trusted_cp.epoch,
+        None,
+    )
     .unwrap();
-    assert_eq!(result, test.expected_result);
+    verify::<
+        SpecTestSpec,
+        PatchedSszStateReader<_, MainnetSpec>,
+        { spec::MAX_VALIDATORS_PER_COMMITTEE },
+        1000,
+        10,
+    >(reader, input)
+}
+
+// the spec test validators use the pyspec test keys, validator i has secret key i + 1
+fn test_secret_key(validator_index: usize) -> blst::min_pk::SecretKey {
+    let mut key = [0_u8; 32];
+    key[24..].copy_from_slice(&(validator_index as u64 + 1).to_be_bytes());
+    blst::min_pk::SecretKey::from_bytes(&key).unwrap()
+}
+
+/// Sign the attestation over its current data with the keys of its participants
+fn resign_attestation(
+    test: &ZiplineTestCase,
+    input: &ZiplineInput<{ spec::MAX_VALIDATORS_PER_COMMITTEE }, 1000, 10>,
+    attestation: &mut Attestation<{ spec::MAX_VALIDATORS_PER_COMMITTEE }>,
+) {
+    const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+    let epoch = SpecTestSpec::epoch(attestation.data.slot as usize) as u64;
+    // the committees of an epoch are computed from the state patched up to the epoch before
+    let reader = input.patches.iter().filter(|p| p.epoch < epoch).fold(
+        PatchedDirectStateReader::new(DirectStateReader::new(test.state.clone())),
+        |reader, patch| reader.with_patch(patch.clone()),
+    );
+    let committee_cache = get_shufflings_for_epoch::<SpecTestSpec, _>(&reader, epoch).unwrap();
+    let committee = committee_cache
+        .get_beacon_committee::<SpecTestSpec>(
+            attestation.data.slot as usize,
+            attestation.data.index as usize,
+        )
+        .unwrap();
+    let participants = get_attesting_indices(committee, attestation).unwrap();
+    let signing_root = attestation_signing_root::<SpecTestSpec>(&mut attestation.data).unwrap();
+    let signatures = participants
+        .iter()
+        .map(|i| test_secret_key(*i).sign(signing_root.as_ref(), DST, &[]))
+        .collect::<Vec<_>>();
+    let signature =
+        blst::min_pk::AggregateSignature::aggregate(&signatures.iter().collect::<Vec<_>>(), false)
+            .unwrap()
+            .to_signature();
+    attestation.signature = signature.to_bytes().to_vec().try_into().unwrap();
+}
+
+/// The active set hint for the given state at an epoch
+fn make_active_set_hint(state: &spec::BeaconState, epoch: u64) -> ActiveSetHint {
+    let is_active = |v: &spec::Validator| v.activation_epoch <= epoch && epoch < v.exit_epoch;
+    ActiveSetHint {
+        active_validators: state.validators.iter().map(is_active).collect(),
+        total_active_balance: state
+            .validators
+            .iter()
+            .filter(|v| is_active(v))
+            .map(|v| v.effective_balance)
+            .sum(),
+    }
+}
+
+/// Treat the given state as the state of the candidate block by pointing the candidate checkpoint at a header for it.
+/// Returns the ancestry proofs for the block root of the slot preceding the state
+fn make_candidate_ancestry(
+    state: &mut spec::BeaconState,
+    candidate_cp: &mut Checkpoint,
+) -> CandidateAncestry {
+    let state_root = state.hash_tree_root().unwrap();
+    let mut header = spec::BeaconBlockHeader {
+        slot: state.slot,
+        state_root,
+        ..Default::default()
+    };
+    let header_root = header.hash_tree_root().unwrap();
+    candidate_cp.root = header_root.as_ref().try_into().unwrap();
+    let state_proof = compute_proof(&header_root, 11, &header.to_merkle_tree().unwrap()).unwrap();
+
+    let state_tree = state.to_merkle_tree().unwrap();
+    let slot_proof =
+        compute_proof(&state_root, SpecTestSpec::SLOT_GINDEX as usize, &state_tree).unwrap();
+
+    let slot = state.slot - 1;
+    let index = slot as usize % SpecTestSpec::slots_per_historical_root();
+    let block_root_proof = compute_proof(
+        &state_root,
+        SpecTestSpec::block_roots_0_gindex() as usize + index,
+        &state_tree,
+    )
+    .unwrap();
+
+    CandidateAncestry {
+        state_root: state_root.as_ref().try_into().unwrap(),
+        state_proof: state_proof.try_into().unwrap(),
+        slot: state.slot,
+        slot_proof: slot_proof.try_into().unwrap(),
+        block_roots: vec![BlockRootProof {
+            slot,
+            root: state.block_roots[index].as_ref().try_into().unwrap(),
+            proof: block_root_proof.try_into().unwrap(),
+        }]
+        .try_into()
+        .unwrap(),
+    }
+}
+
+/// Combine the attestations for the epochs covered by the input into a super attestation with a single aggregate signature
+fn to_super_attestation(
+    input: &ZiplineInput<{ spec::MAX_VALIDATORS_PER_COMMITTEE }, 1000, 10>,
+) -> SuperAttestation<{ spec::MAX_VALIDATORS_PER_COMMITTEE }, 1000> {
+    let first_epoch = input.trusted_cp.epoch + 1;
+    let last_epoch = input.trusted_cp.epoch + input.patches.len() as u64 + 1;
+
+    let mut checkpoints = Vec::<Checkpoint>::new();
+    let mut attestations = Vec::new();
+    let mut signatures = Vec::new();
+    for a in input.attestations.iter() {
+        let epoch = SpecTestSpec::epoch(a.data.slot as usize) as u64;
+        if epoch < first_epoch || epoch > last_epoch {
+            continue;
+        }
+        let mut checkpoint_id = |cp: Checkpoint| match checkpoints.iter().position(|c| *c == cp) {
+            Some(id) => id as u32,
+            None => {
+                checkpoints.push(cp);
+                checkpoints.len() as u32 - 1
+            }
+        };
+        attestations.push(CompressedAttestation {
+            data: CompressedAttestationData {
+                slot: a.data.slot,
+                beacon_block_root: a.data.beacon_block_root,
+                source: checkpoint_id(a.data.source),
+                target: checkpoint_id(a.data.target),
+            },
+            participation: vec![Participation {
+                bitlist: a.aggregation_bits.clone(),
+                committee_index: a.data.index,
+            }]
+            .try_into()
+            .unwrap(),
+        });
+        signatures.push(Signature::from_bytes(&a.signature).unwrap());
+    }
+
+    SuperAttestation {
+        attestations: attestations.try_into().unwrap(),
+        checkpoints: checkpoints.try_into().unwrap(),
+        signature: Signature::aggregate(&signatures)
+            .unwrap()
+            .to_bytes()
+            .to_vec()
+            .try_into()
+            .unwrap(),
+    }
 }
 
 fn run_test_native_ssz(mut test: ZiplineTestCase) {
@@ -310,20 +1317,35 @@ fn run_test_native_ssz(mut test: ZiplineTestCase) {
 
     let input = test.to_input();
     let oracle_provider = make_test_oracle_provider(&input, &mut test.state);
-    let hashmap_oracle = HashMapOracle::from(oracle_provider);
-
-    let reader =
-        SszStateReader::new(hashmap_oracle, state_root.as_ref().try_into().unwrap()).unwrap();
+    let state_root: [u8; 32] = state_root.as_ref().try_into().unwrap();
 
-    let result = verify::<
-        SpecTestSpec,
-        PatchedSszStateReader<_, MainnetSpec>,
-        { spec::MAX_VALIDATORS_PER_COMMITTEE },
-        _,
-        _,
-    >(reader, input)
+    // loading validators up front or only as they are needed must give the same result
+    let epoch = input.trusted_cp.epoch;
+    let eager = SszStateReader::new(
+        HashMapOracle::from(oracle_provider.clone()),
+        state_root,
+        epoch,
+    )
     .unwrap();
-    assert_eq!(result, test.expected_result);
+    let lazy = SszStateReader::lazy(
+        HashMapOracle::from(oracle_provider),
+        state_root,
+        epoch,
+        None,
+    )
+    .unwrap();
+
+    for reader in [eager, lazy] {
+        let result = verify::<
+            SpecTestSpec,
+            PatchedSszStateReader<_, MainnetSpec>,
+            { spec::MAX_VALIDATORS_PER_COMMITTEE },
+            _,
+            _,
+        >(reader, input.clone())
+        .unwrap();
+        assert_eq!(result.is_finalized(), test.expected_result);
+    }
 }
 
 fn run_test_unicorn(mut test: ZiplineTestCase) {
@@ -350,13 +1372,16 @@ fn run_test_unicorn(mut test: ZiplineTestCase) {
     println!("steps: {:?}", steps);
     println!("emulation_output: {:?}", emulation_output);
 
-    let mut expected_result = [0xff_u8; 68];
-    expected_result[..4].copy_from_slice(&[0x13, 0x37, 0xf0, 0x0d]);
-    if test.expected_result {
-        expected_result[4..].copy_from_slice(&[0x00; 64]);
-    }
-
-    assert_eq!(emulation_output, expected_result);
+    let verdict = if test.expected_result {
+        Verdict::Finalized
+    } else {
+        Verdict::NotFinalized
+    };
+    assert_eq!(emulation_output[..4], [0x13, 0x37, 0xf0, 0x0d]);
+    assert_eq!(
+        emulation_output[4..36],
+        expected_output_hash(&input, verdict)
+    );
 }
 
 fn make_test_oracle_provider<
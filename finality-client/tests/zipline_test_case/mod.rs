@@ -11,7 +11,7 @@ use test_utils::{load_snappy_ssz, load_yaml, Config};
 use validator_shuffling::get_randao_index;
 use zipline_finality_client::attestation::Checkpoint;
 use zipline_finality_client::input::ZiplineInput;
-use zipline_finality_client::state_patch::StatePatch;
+use zipline_finality_client::state_patch::{StatePatch, ValidatorRecord};
 use zipline_spec::Spec;
 
 type Attestation =
@@ -170,7 +170,7 @@ impl ZiplineTestCase {
                     attestations: attestation_accumulator.to_vec().try_into().unwrap(),
                     patches: patches
                         .iter()
-                        .filter(|p| p.epoch >= candidate.epoch)
+                        .filter(|p| p.epoch > current_trusted.checkpoint.epoch)
                         .cloned()
                         .collect::<Vec<_>>()
                         .try_into()
@@ -231,6 +231,10 @@ impl ZiplineTestCase {
             patches: self.patches.clone(),
             attestations: self.attestations.clone(),
             state_proof: self.state_proof.clone(),
+            candidate_ancestry: Default::default(),
+            super_attestation: Default::default(),
+            uncompressed_pubkeys_root: Default::default(),
+            active_set_hint: Default::default(),
         }
     }
 }
@@ -286,11 +290,35 @@ pub fn patch_from_states<S: Spec>(
         },
     );
 
+    // validators newly slashed since the before state
+    let slashings: Vec<u32> = after
+        .validators
+        .iter()
+        .enumerate()
+        .filter(|(i, validator)| {
+            validator.slashed && before.validators.get(*i).is_some_and(|v| !v.slashed)
+        })
+        .map(|(i, _)| i as u32)
+        .collect();
+
+    // records for the validators created by deposits. The record has the same encoding as a Validator
+    let new_validators: Vec<ValidatorRecord> = after
+        .validators
+        .iter()
+        .skip(before.validators.len())
+        .map(|validator| {
+            ValidatorRecord::deserialize(&ssz_rs::serialize(validator).unwrap()).unwrap()
+        })
+        .collect();
+
     StatePatch {
         epoch: after_epoch,
         randao_next: randao[get_randao_index::<S>(after_epoch + 1)], // TODO: actually retrieve the correct one for the epoch
         n_deposits_processed: (after.validators.len() - before.validators.len()) as u32,
         activations: activations.try_into().unwrap(),
         exits: exits.try_into().unwrap(),
+        effective_balance_updates: Default::default(),
+        slashings: slashings.try_into().unwrap(),
+        new_validators: new_validators.try_into().unwrap(),
     }
 }
@@ -3,7 +3,7 @@ import { ssz } from "@lodestar/types";
 import { SignedBeaconBlock } from "@lodestar/types/allForks";
 import { Attestation, BeaconBlockHeader, Checkpoint } from "@lodestar/types/phase0";
 import { toHex, toHexString } from "@lodestar/utils";
-import { ContainerType, ListBasicType, UintNumberType, ValueOf, byteArrayEquals } from "@chainsafe/ssz";
+import { ContainerType, ListBasicType, ListCompositeType, UintNumberType, ValueOf, byteArrayEquals } from "@chainsafe/ssz";
 import {
   BeaconStateAllForks,
   blockToHeader,
@@ -16,7 +16,12 @@ import {
   stateTransition,
   getRandaoMix,
 } from "@lodestar/state-transition";
-import { EPOCHS_PER_HISTORICAL_VECTOR, MIN_SEED_LOOKAHEAD, SLOTS_PER_EPOCH } from "@lodestar/params";
+import {
+  EFFECTIVE_BALANCE_INCREMENT,
+  EPOCHS_PER_HISTORICAL_VECTOR,
+  MIN_SEED_LOOKAHEAD,
+  SLOTS_PER_EPOCH,
+} from "@lodestar/params";
 import { BeaconConfig } from "@lodestar/config";
 import { getBlockHeader, getBlocks, getState } from "./api.js";
 import { log } from "./logger.js";
@@ -25,9 +30,20 @@ import { writeFile } from "node:fs/promises";
 // Copied from https://github.com/ChainSafe/Zipline-Casper/blob/f37d2c5c7be81a5ae6c203c9c60e8dba0968e017/zipline-verifier/src/state_patch.rs#L15
 const MAX_ACTIVATIONS = 256;
 const MAX_EXITS = 256;
+const MAX_BALANCE_UPDATES = 1024;
+const MAX_SLASHINGS = 1024;
+const MAX_NEW_VALIDATORS = 512;
 
 const U32 = new UintNumberType(4);
 
+export const EffectiveBalanceUpdateType = new ContainerType(
+  {
+    validatorIndex: U32,
+    effectiveBalance: ssz.Gwei,
+  },
+  { typeName: "EffectiveBalanceUpdate", jsonCase: "eth2" }
+);
+
 export const StatePatchType = new ContainerType(
   {
     epoch: ssz.Epoch,
@@ -35,6 +51,9 @@ export const StatePatchType = new ContainerType(
     exits: new ListBasicType(U32, MAX_EXITS),
     nDepositsProcessed: U32,
     randaoNext: ssz.Root,
+    effectiveBalanceUpdates: new ListCompositeType(EffectiveBalanceUpdateType, MAX_BALANCE_UPDATES),
+    slashings: new ListBasicType(U32, MAX_SLASHINGS),
+    newValidators: new ListCompositeType(ssz.phase0.Validator, MAX_NEW_VALIDATORS),
   },
   { typeName: "StatePatch", jsonCase: "eth2", cachePermanentRootStruct: true }
 );
@@ -62,11 +81,38 @@ export function createStatePatch(stateA: BeaconStateAllForks, stateB: BeaconStat
     }
   }
   const nDepositsProcessed = stateB.validators.length - stateA.validators.length;
+  const newValidators = [];
+  for (let i = stateA.validators.length; i < stateB.validators.length; i++) {
+    newValidators.push(stateB.validators.get(i).toValue());
+  }
+  // the verifier only accepts decreases of a single increment. Larger decreases can only happen to validators that
+  // have already exited so they are skipped, as is anything over the list limit
+  const effectiveBalanceUpdates = [];
+  const slashings = [];
+  for (let i = 0; i < stateA.validators.length; i++) {
+    if (stateB.validators.get(i).slashed && !stateA.validators.get(i).slashed) {
+      slashings.push(i);
+    }
+  }
+  for (let i = 0; i < stateA.validators.length; i++) {
+    const before = stateA.validators.get(i).effectiveBalance;
+    const after = stateB.validators.get(i).effectiveBalance;
+    if (before === after) {
+      continue;
+    }
+    if (after + EFFECTIVE_BALANCE_INCREMENT < before) {
+      log.warn(`Skipping effective balance decrease of validator ${i} from ${before} to ${after}`);
+    } else if (effectiveBalanceUpdates.length >= MAX_BALANCE_UPDATES) {
+      log.warn(`Skipping effective balance update of validator ${i}, patch is full`);
+    } else {
+      effectiveBalanceUpdates.push({ validatorIndex: i, effectiveBalance: after });
+    }
+  }
   const randaoIndex = epoch + EPOCHS_PER_HISTORICAL_VECTOR - MIN_SEED_LOOKAHEAD;
   const randaoNext = getRandaoMix(stateB, randaoIndex);
 
   log.info(
-    `Created state patch epoch ${epoch}, activations ${activations}, exits ${exits}, ndeposits ${nDepositsProcessed}, randaoNext ${randaoNext} }`
+    `Created state patch epoch ${epoch}, activations ${activations}, exits ${exits}, ndeposits ${nDepositsProcessed}, balance updates ${effectiveBalanceUpdates.length}, slashings ${slashings}, randaoNext ${randaoNext} }`
   );
 
   return {
@@ -75,6 +121,9 @@ export function createStatePatch(stateA: BeaconStateAllForks, stateB: BeaconStat
     exits,
     nDepositsProcessed,
     randaoNext,
+    effectiveBalanceUpdates,
+    slashings,
+    newValidators,
   };
 }
 
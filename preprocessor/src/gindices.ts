@@ -1,6 +1,6 @@
 /* eslint-disable @typescript-eslint/no-unused-vars */
 import { Gindex } from "@chainsafe/persistent-merkle-tree";
-import { EPOCHS_PER_HISTORICAL_VECTOR } from "@lodestar/params";
+import { EPOCHS_PER_HISTORICAL_VECTOR, SLOTS_PER_EPOCH, SLOTS_PER_HISTORICAL_ROOT } from "@lodestar/params";
 
 // ssz.phase0.BeaconState.getPathInfo(['validators'])
 const VALIDATORS_ROOT_GINDEX = 43n;
@@ -44,6 +44,20 @@ export function getValidatorGindices(validatorIndex: number): {
 
 export const VALIDATORS_LENGTH_GINDEX = VALIDATORS_ROOT_GINDEX * 2n + 1n;
 
+// ssz.phase0.BeaconState.getPathInfo(['slot']).gindex
+export const SLOT_GINDEX = 34n;
+
+// ssz.phase0.BeaconState.getPathInfo(['blockRoots']).gindex
+const BLOCK_ROOTS_ROOT_GINDEX = 37n;
+// ssz.phase0.BeaconState.fields.blockRoots.depth
+const BLOCK_ROOTS_DEPTH = Math.log2(SLOTS_PER_HISTORICAL_ROOT);
+// Index of BlockRoots[0]
+const BLOCK_ROOTS_0_GINDEX = BLOCK_ROOTS_ROOT_GINDEX * 2n ** BigInt(BLOCK_ROOTS_DEPTH);
+
+export function getEpochBlockRootGindex(epoch: number): Gindex {
+  return BLOCK_ROOTS_0_GINDEX + BigInt((epoch * SLOTS_PER_EPOCH) % SLOTS_PER_HISTORICAL_ROOT);
+}
+
 // ssz.phase0.BeaconState.getPathInfo(['justificationBits']).gindex
 export const JUSTIFICATION_BITS_GINDEX = 49n;
 
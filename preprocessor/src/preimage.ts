@@ -8,15 +8,17 @@ import {
   CURRENT_JUSTIFIED_CHECKPOINT_GINDEX,
   FINALIZED_CHECKPOINT_GINDEX,
   getCheckpointGindices,
+  getEpochBlockRootGindex,
   getEpochRandaoMixGindex,
   getValidatorGindices,
   JUSTIFICATION_BITS_GINDEX,
   PREVIOUS_JUSTIFIED_CHECKPOINT_GINDEX,
+  SLOT_GINDEX,
   VALIDATORS_LENGTH_GINDEX,
 } from "./gindices.js";
 import { log } from "./logger.js";
 import { BeaconStateAllForks } from "@lodestar/state-transition";
-import { EPOCHS_PER_HISTORICAL_VECTOR, MIN_SEED_LOOKAHEAD } from "@lodestar/params";
+import { EPOCHS_PER_HISTORICAL_VECTOR, MIN_SEED_LOOKAHEAD, SLOTS_PER_EPOCH } from "@lodestar/params";
 // String is a hex string
 export type PreimageMap = Map<string, Uint8Array>;
 
@@ -62,7 +64,8 @@ function addPathsGindices(gindices: Gindex[], pathGindices?: Set<Gindex>): Set<G
 
 function getZiplineGindices(
   validatorLength: number,
-  epoch: Epoch
+  epoch: Epoch,
+  stateSlot: number
 ): {
   checkpoint: Set<Gindex>;
   header: Set<Gindex>;
@@ -89,11 +92,18 @@ function getZiplineGindices(
     addPathsGindices([pubkey, activationEpoch, exitEpoch, balance], state);
   }
   const randaoIndex = epoch + EPOCHS_PER_HISTORICAL_VECTOR - MIN_SEED_LOOKAHEAD;
+  // roots of the checkpoints the justification bits refer to. These are the 4 epochs before the state's epoch
+  const stateEpoch = Math.floor(stateSlot / SLOTS_PER_EPOCH);
+  const justificationBlockRoots = [1, 2, 3, 4]
+    .filter((i) => stateEpoch >= i)
+    .map((i) => getEpochBlockRootGindex(stateEpoch - i));
   log.info("getting other gindices");
   addPathsGindices(
     [
       VALIDATORS_LENGTH_GINDEX,
+      SLOT_GINDEX,
       JUSTIFICATION_BITS_GINDEX,
+      ...justificationBlockRoots,
       ...Object.values(getCheckpointGindices(PREVIOUS_JUSTIFIED_CHECKPOINT_GINDEX)),
       ...Object.values(getCheckpointGindices(CURRENT_JUSTIFIED_CHECKPOINT_GINDEX)),
       ...Object.values(getCheckpointGindices(FINALIZED_CHECKPOINT_GINDEX)),
@@ -137,7 +147,7 @@ export function getZiplinePreimages(
     checkpoint: checkpointGindices,
     header: headerGindices,
     state: stateGindices,
-  } = getZiplineGindices(state.validators.length, checkpoint.epoch);
+  } = getZiplineGindices(state.validators.length, checkpoint.epoch, state.slot);
 
   const preimages: PreimageMap = new Map();
   log.info("get checkpoint preimages");
@@ -5,17 +5,50 @@ import { BeaconConfig } from "@lodestar/config";
 import { Api } from "@lodestar/api";
 import { Epoch, Root, ssz } from "@lodestar/types";
 import { BeaconBlockHeader, Checkpoint } from "@lodestar/types/phase0";
-import { BeaconStateAllForks, computeEndSlotAtEpoch, computeStartSlotAtEpoch } from "@lodestar/state-transition";
+import {
+  BeaconStateAllForks,
+  computeEndSlotAtEpoch,
+  computeEpochAtSlot,
+  computeStartSlotAtEpoch,
+} from "@lodestar/state-transition";
 
 import { StatePatchType, getNextFinalizedData } from "./chainFinality.js";
 import { getZiplinePreimages, writePreimages } from "./preimage.js";
 import { Attestations, compressAttestations, SuperAttestationType } from "./superAggregate.js";
 import { getBlocks } from "./api.js";
-import { ContainerType, ListCompositeType, ValueOf } from "@chainsafe/ssz";
+import { BitArray, BitListType, ContainerType, ListCompositeType, ValueOf } from "@chainsafe/ssz";
 import { MAX_ATTESTATIONS } from "@lodestar/params";
 import { Gindex, Tree } from "@chainsafe/persistent-merkle-tree";
 import { log } from "./logger.js";
 
+export const BlockRootProofType = new ContainerType(
+  {
+    slot: ssz.Slot,
+    root: ssz.Root,
+    proof: new ListCompositeType(ssz.Root, 32),
+  },
+  { typeName: "BlockRootProof", jsonCase: "eth2" }
+);
+
+export const CandidateAncestryType = new ContainerType(
+  {
+    stateRoot: ssz.Root,
+    stateProof: new ListCompositeType(ssz.Root, 3),
+    slot: ssz.Slot,
+    slotProof: new ListCompositeType(ssz.Root, 32),
+    blockRoots: new ListCompositeType(BlockRootProofType, 8192),
+  },
+  { typeName: "CandidateAncestry", jsonCase: "eth2" }
+);
+
+export const ActiveSetHintType = new ContainerType(
+  {
+    activeValidators: new BitListType(2 ** 24),
+    totalActiveBalance: ssz.UintNum64,
+  },
+  { typeName: "ActiveSetHint", jsonCase: "eth2" }
+);
+
 export const ZiplineInputType = new ContainerType(
   {
     trustedCp: ssz.phase0.Checkpoint,
@@ -24,12 +57,26 @@ export const ZiplineInputType = new ContainerType(
     patches: new ListCompositeType(StatePatchType, 256),
     attestations: new ListCompositeType(ssz.phase0.Attestation, MAX_ATTESTATIONS),
     stateProof: new ListCompositeType(ssz.Root, 3),
+    candidateAncestry: CandidateAncestryType,
+    superAttestation: SuperAttestationType,
+    uncompressedPubkeysRoot: ssz.Root,
+    activeSetHint: ActiveSetHintType,
   },
   { typeName: "ZiplineInput", jsonCase: "eth2", cachePermanentRootStruct: true }
 );
 
 export type ZiplineInput = ValueOf<typeof ZiplineInputType>;
 
+function getActiveSetHint(state: BeaconStateAllForks, epoch: Epoch): ValueOf<typeof ActiveSetHintType> {
+  const active = state.validators
+    .getAllReadonlyValues()
+    .map((v) => v.activationEpoch <= epoch && epoch < v.exitEpoch);
+  const totalActiveBalance = state.validators
+    .getAllReadonlyValues()
+    .reduce((total, v, i) => (active[i] ? total + v.effectiveBalance : total), 0);
+  return { activeValidators: BitArray.fromBoolArray(active), totalActiveBalance };
+}
+
 function getStateProof(header: BeaconBlockHeader): [Root, Root, Root] {
   const headerView = ssz.phase0.BeaconBlockHeader.toView(header);
   const gindex = ssz.phase0.BeaconBlockHeader.getPropertyGindex("stateRoot") as Gindex;
@@ -60,7 +107,16 @@ export async function preprocessUpdate(
     trustedViewDU
   );
 
-  const superAttestation = compressAttestations(attestations);
+  // The finality client only verifies attestations for the epochs covered by the patches. Any others must be left out
+  // of the super attestation or its aggregate signature will not verify
+  const firstEpoch = trustedCheckpoint.epoch + 1;
+  const lastEpoch = trustedCheckpoint.epoch + statePatches.length + 1;
+  const superAttestation = compressAttestations(
+    attestations.filter((a) => {
+      const epoch = computeEpochAtSlot(a.data.slot);
+      return epoch >= firstEpoch && epoch <= lastEpoch;
+    })
+  );
   const preimages = getZiplinePreimages(config, trustedCheckpoint, trustedHeader, trustedState);
 
   const stateProof = getStateProof(trustedHeader);
@@ -73,8 +129,15 @@ export async function preprocessUpdate(
     trustedCp: trustedCheckpoint,
     candidateCp: checkpoint,
     patches: statePatches,
-    attestations,
+    // all attestations are provided in the super attestation to avoid submitting a signature for each
+    attestations: [],
     stateProof,
+    // no intermediate block roots are relayed yet
+    candidateAncestry: CandidateAncestryType.defaultValue(),
+    superAttestation,
+    // zero means the finality client decompresses the keys from the state itself
+    uncompressedPubkeysRoot: new Uint8Array(32),
+    activeSetHint: getActiveSetHint(trustedState, trustedCheckpoint.epoch),
   };
   await writeFile(path.join(directory, "input.ssz"), ZiplineInputType.serialize(input));
   return {
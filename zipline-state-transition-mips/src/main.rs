@@ -4,14 +4,23 @@
 #![feature(core_intrinsics)]
 #![feature(alloc_error_handler)]
 
-use preimage_oracle::PreimageOracle;
+use preimage_oracle::{PreimageOracle, H256};
 #[cfg(feature = "mainnet")]
 use zipline_spec::MainnetSpec as Spec;
 #[cfg(feature = "minimal")]
 use zipline_spec::MinimalSpec as Spec;
 #[cfg(feature = "spec_test")]
 use zipline_spec::SpecTestSpec as Spec;
+#[cfg(feature = "gnosis")]
+use zipline_spec::GnosisSpec as Spec;
+#[cfg(feature = "chiado")]
+use zipline_spec::ChiadoSpec as Spec;
+#[cfg(feature = "holesky")]
+use zipline_spec::HoleskySpec as Spec;
+#[cfg(feature = "sepolia")]
+use zipline_spec::SepoliaSpec as Spec;
 use zipline_finality_client::input::ZiplineInput;
+use zipline_finality_client::output::{Verdict, ZiplineOutput};
 use zipline_finality_client::ssz_state_reader::{PatchedSszStateReader, SszStateReader};
 
 extern crate alloc;
@@ -45,24 +54,38 @@ pub extern "C" fn _start() {
     let _ = log::set_logger(&LOGGER).map(|()| log::set_max_level(LevelFilter::Trace));
     log::debug!("Zipline state transition start");
 
+    let input_hash = iommu::input_hash();
+    let mut output = run(input_hash).unwrap_or_else(|| {
+        log::error!("Input rejected as invalid");
+        ZiplineOutput::invalid_input(input_hash)
+    });
+    iommu::output(output.hash().unwrap());
+}
+
+/// Verify the input with the given hash returning None if it is invalid
+fn run(input_hash: H256) -> Option<ZiplineOutput> {
     let oracle = iommu::preimage_oracle();
     // load our input struct from the preimage oracle by its hash
-    let input_bytes = oracle.get_cached(iommu::input_hash()).unwrap();
-    let input = ZiplineInput::from_ssz_bytes(input_bytes);
-    let state_reader = SszStateReader::<_, Spec>::new(oracle, input.state_root).unwrap();
+    let input_bytes = oracle.get_cached(input_hash)?;
+    let input = ZiplineInput::from_ssz_bytes(input_bytes)
+        .map_err(|e| log::error!("Failed to decode input: {:?}", e))
+        .ok()?;
+    let candidate_cp = input.candidate_cp;
+    // only the keys of validators in attesting committees are loaded. The state is the one of the trusted checkpoint
+    let state_reader = SszStateReader::<_, Spec>::lazy(oracle, input.state_root, input.trusted_cp.epoch, input.uncompressed_pubkeys())
+        .map_err(|e| log::error!("Failed to read trusted state: {:?}", e))
+        .ok()?;
 
-    let result = zipline_finality_client::verify::<Spec, PatchedSszStateReader<_, Spec>, 2048, 10000, 256>(
+    let outcome = zipline_finality_client::verify::<Spec, PatchedSszStateReader<_, Spec>, 2048, 10000, 256>(
         state_reader,
         input,
-    );
-    
-    if result.is_ok() {
-        iommu::output([0x00; 32]);
-    } else {
-        iommu::output([0xff; 32]);
-    }
+    )
+    .map_err(|e| log::error!("Verification failed: {:?}", e))
+    .ok()?;
 
-    // iommu::output([0x00; 32]);
+    log::info!("Verification outcome: {:?}", outcome);
+    // commit to the input, the candidate and the verdict so a verifier can tell them apart
+    Some(ZiplineOutput::new(input_hash, candidate_cp, Verdict::from(&outcome)))
 }
 
 #[panic_handler]